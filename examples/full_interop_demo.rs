@@ -4,13 +4,15 @@ use std::rc::Rc;
 // Import from our library
 use rustlisp2::{
     Environment,
+    LispError,
     Value,
     eval,
     read,
     print_value,
     setup_environment,
     setup_rust_functions,
-    register_rust_function,
+    define_rust_function,
+    define_typed_function,
     register_lisp_function,
     call_lisp_function,
     rust_to_lisp_number,
@@ -28,7 +30,7 @@ fn main() {
     setup_rust_functions(env.clone());
     
     // Register some example Rust functions
-    register_rust_functions();
+    register_rust_functions(&env);
     
     // Demo Lisp -> Rust interop
     demo_lisp_to_rust(&env);
@@ -41,51 +43,53 @@ fn main() {
     repl(env);
 }
 
-fn register_rust_functions() {
-    // Register our example Rust functions
-    register_rust_function("rust-add", rust_add);
-    register_rust_function("rust-multiply", rust_multiply);
-    register_rust_function("rust-length", rust_length);
-    register_rust_function("rust-uppercase", rust_uppercase);
+fn register_rust_functions(env: &Rc<Environment>) {
+    // Bind our example Rust functions as ordinary Lisp procedures
+    // Typed functions get their argument checks and conversions generated
+    define_typed_function(env, "rust-add", rust_add);
+    define_typed_function(env, "rust-multiply", rust_multiply);
+    define_typed_function(env, "rust-length", rust_length);
+    define_typed_function(env, "rust-uppercase", rust_uppercase);
     
     // You can also register custom functions inline
-    register_rust_function("rust-square", |args| {
-        if args.len() != 1 {
-            println!("rust-square requires exactly one argument");
-            return Rc::new(Value::Nil);
+    define_rust_function(env, "rust-square", |args| {
+        match args.as_slice() {
+            [arg] => match &**arg {
+                Value::Number(n) => Ok(Rc::new(Value::Number(n * n))),
+                _ => Err(LispError::Custom("rust-square requires a numeric argument".to_string())),
+            },
+            _ => Err(LispError::Custom("rust-square requires exactly one argument".to_string())),
         }
-        
-        if let Value::Number(n) = &*args[0] {
-            return Rc::new(Value::Number(n * n));
-        }
-        
-        println!("rust-square requires a numeric argument");
-        Rc::new(Value::Nil)
     });
+
 }
 
 fn demo_lisp_to_rust(env: &Rc<Environment>) {
     println!("=== Demo: Calling Rust from Lisp ===");
-    println!("You can call Rust functions from Lisp using the 'rust-call' special form.\n");
+    println!("Rust functions are ordinary Lisp procedures; the 'rust-call' special form also works.\n");
     
     // Example 1: Simple Rust function call
-    let example1 = "(rust-call rust-add 1 2 3)";
+    let example1 = "(rust-add 1 2 3)";
     println!("Lisp code: {}", example1);
     match read(example1) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
     
     // Example 2: Using Rust functions in Lisp expressions
-    let example2 = "(+ (rust-call rust-square 3) (rust-call rust-square 4))";
+    let example2 = "(+ (rust-square 3) ((car (list rust-square)) 4))";
     println!("Lisp code: {}", example2);
     match read(example2) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
@@ -95,8 +99,10 @@ fn demo_lisp_to_rust(env: &Rc<Environment>) {
     println!("Lisp code: {}", example3);
     match read(example3) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}", print_value(&result)),
+                Err(err) => println!("Error: {}", err),
+            }
         }
         Err(err) => println!("Error: {}", err),
     }
@@ -117,8 +123,10 @@ fn demo_lisp_to_rust(env: &Rc<Environment>) {
     println!("Lisp code: {}", example4);
     match read(example4) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
@@ -145,7 +153,13 @@ fn demo_rust_to_lisp(env: &Rc<Environment>) {
     // Now, call this function from Rust
     println!("\nCalling Lisp 'square' function from Rust with argument 5...");
     let args = vec![rust_to_lisp_number(5.0)];
-    let result = call_lisp_function("square", args, env.clone());
+    let result = match call_lisp_function("square", args, env.clone()) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Error: {}", err);
+            return;
+        }
+    };
     
     // Convert the result back to a Rust value
     match lisp_to_rust_number(&result) {
@@ -173,7 +187,13 @@ fn demo_rust_to_lisp(env: &Rc<Environment>) {
         rust_to_lisp_number(3.0),
         rust_to_lisp_number(4.0),
     ];
-    let result = call_lisp_function("sum-of-squares", args, env.clone());
+    let result = match call_lisp_function("sum-of-squares", args, env.clone()) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Error: {}", err);
+            return;
+        }
+    };
     
     match lisp_to_rust_number(&result) {
         Some(n) => println!("Result: {} (Rust f64 value)", n),
@@ -195,14 +215,16 @@ fn repl(env: Rc<Environment>) {
         stdout.flush().unwrap();
         
         let mut input = String::new();
-        if stdin.lock().read_line(&mut input).is_err() || input.trim() == "exit" {
+        if matches!(stdin.lock().read_line(&mut input), Ok(0) | Err(_)) || input.trim() == "exit" {
             break;
         }
         
         match read(&input) {
             Ok(expr) => {
-                let result = eval(expr, env.clone());
-                println!("{}", print_value(&result));
+                match eval(expr, env.clone()) {
+                    Ok(result) => println!("{}", print_value(&result)),
+                    Err(err) => println!("Error: {}", err),
+                }
             }
             Err(err) => {
                 println!("Error: {}", err);
@@ -1,8 +1,6 @@
 use std::rc::Rc;
 use rustlisp2::{
     Environment,
-    eval,
-    read,
     print_value,
     setup_environment,
     call_lisp_function,
@@ -39,7 +37,13 @@ fn demo_interop(env: &Rc<Environment>) {
     // Now, let's call this function from Rust
     println!("\nCalling Lisp 'square' function from Rust with argument 5...");
     let args = vec![rust_to_lisp_number(5.0)];
-    let result = call_lisp_function("square", args, env.clone());
+    let result = match call_lisp_function("square", args, env.clone()) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Error: {}", err);
+            return;
+        }
+    };
     
     // Convert the result back to a Rust value
     match lisp_to_rust_number(&result) {
@@ -67,7 +71,13 @@ fn demo_interop(env: &Rc<Environment>) {
         rust_to_lisp_number(3.0),
         rust_to_lisp_number(4.0),
     ];
-    let result = call_lisp_function("sum-of-squares", args, env.clone());
+    let result = match call_lisp_function("sum-of-squares", args, env.clone()) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Error: {}", err);
+            return;
+        }
+    };
     
     match lisp_to_rust_number(&result) {
         Some(n) => println!("Result: {} (Rust f64 value)", n),
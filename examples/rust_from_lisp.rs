@@ -1,7 +1,10 @@
+use std::cell::Cell;
 use std::rc::Rc;
 use rustlisp2::{
     Environment,
+    LispError,
     Value,
+    car,
     eval,
     read,
     print_value,
@@ -18,6 +21,9 @@ fn main() {
     
     // Register some custom Rust functions
     register_custom_functions();
+
+    // Closures can carry state of their own
+    register_stateful_functions(env.clone());
     
     // Define and run some Lisp code that calls Rust functions
     run_lisp_examples(env.clone());
@@ -27,51 +33,64 @@ fn register_custom_functions() {
     // Register a simple Rust function that squares a number
     register_rust_function("square", |args| {
         if args.len() != 1 {
-            println!("square requires exactly one argument");
-            return Rc::new(Value::Nil);
+            return Err(LispError::ArityMismatch {
+                name: "square".to_string(),
+                expected: "1".to_string(),
+                got: args.len(),
+            });
         }
         
-        if let Value::Number(n) = &*args[0] {
-            return Rc::new(Value::Number(n * n));
+        match &*args[0] {
+            Value::Number(n) => Ok(Rc::new(Value::Number(n * n))),
+            _ => Err(LispError::TypeMismatch {
+                expected: "number".to_string(),
+                got: print_value(&args[0]),
+            }),
         }
-        
-        println!("square requires a numeric argument");
-        Rc::new(Value::Nil)
     });
     
-    // Register a Rust function that concatenates symbols
+    // Register a Rust function that concatenates strings
     register_rust_function("concat", |args| {
         let mut result = String::new();
         
         for arg in args {
-            if let Value::Symbol(s) = &*arg {
-                result.push_str(s);
-            } else {
-                println!("concat requires symbol arguments");
-                return Rc::new(Value::Nil);
+            match &*arg {
+                Value::String(s) => result.push_str(s),
+                _ => return Err(LispError::Custom("concat requires string arguments".to_string())),
             }
         }
         
-        Rc::new(Value::Symbol(result))
+        Ok(Rc::new(Value::String(result)))
     });
     
     // Register a Rust function that checks if a number is even
     register_rust_function("is-even", |args| {
-        if args.len() != 1 {
-            println!("is-even requires exactly one argument");
-            return Rc::new(Value::Nil);
-        }
-        
-        if let Value::Number(n) = &*args[0] {
-            let is_even = (*n as i64) % 2 == 0;
-            return Rc::new(Value::Bool(is_even));
+        match args.as_slice() {
+            [arg] => match &**arg {
+                Value::Number(n) => Ok(Rc::new(Value::Bool(n.to_i64().is_some_and(|n| n % 2 == 0)))),
+                _ => Err(LispError::Custom("is-even requires a numeric argument".to_string())),
+            },
+            _ => Err(LispError::Custom("is-even requires exactly one argument".to_string())),
         }
-        
-        println!("is-even requires a numeric argument");
-        Rc::new(Value::Nil)
     });
 }
 
+fn register_stateful_functions(env: Rc<Environment>) {
+    // A rust-call target that hands out increasing ids
+    let next_id = Rc::new(Cell::new(0));
+    register_rust_function("next-id", move |_| {
+        next_id.set(next_id.get() + 1);
+        Ok(Rc::new(Value::Number(next_id.get().into())))
+    });
+
+    // A procedure Lisp can call directly, closed over its configuration
+    let greeting = String::from("Hello");
+    env.define("greet", Value::procedure("greet", move |args, _| {
+        let name = print_value(&car(&args));
+        Ok(Rc::new(Value::String(format!("{}, {}!", greeting, name))))
+    }));
+}
+
 fn run_lisp_examples(env: Rc<Environment>) {
     println!("=== Calling Rust Functions from Lisp ===\n");
     
@@ -80,19 +99,23 @@ fn run_lisp_examples(env: Rc<Environment>) {
     println!("Lisp code: {}", example1);
     match read(example1) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
     
     // Example 2: Call the concat function
-    let example2 = "(rust-call concat hello world)";
+    let example2 = "(rust-call concat \"hello\" \"world\")";
     println!("Lisp code: {}", example2);
     match read(example2) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
@@ -102,8 +125,10 @@ fn run_lisp_examples(env: Rc<Environment>) {
     println!("Lisp code: {}", example3);
     match read(example3) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
@@ -113,8 +138,10 @@ fn run_lisp_examples(env: Rc<Environment>) {
     println!("Lisp code: {}", example4);
     match read(example4) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}", print_value(&result)),
+                Err(err) => println!("Error: {}", err),
+            }
         }
         Err(err) => println!("Error: {}", err),
     }
@@ -124,11 +151,28 @@ fn run_lisp_examples(env: Rc<Environment>) {
     println!("Lisp code: {}", example5);
     match read(example5) {
         Ok(expr) => {
-            let result = eval(expr, env.clone());
-            println!("Result: {}\n", print_value(&result));
+            match eval(expr, env.clone()) {
+                Ok(result) => println!("Result: {}\n", print_value(&result)),
+                Err(err) => println!("Error: {}\n", err),
+            }
         }
         Err(err) => println!("Error: {}\n", err),
     }
     
+    // Example 6: Stateful Rust functions
+    // Errors returned by a Rust function are reported like any other
+    for example in ["(rust-call next-id)", "(rust-call next-id)", "(greet 'world)", "(rust-call square \"five\")"] {
+        println!("Lisp code: {}", example);
+        match read(example) {
+            Ok(expr) => {
+                match eval(expr, env.clone()) {
+                    Ok(result) => println!("Result: {}\n", print_value(&result)),
+                    Err(err) => println!("Error: {}\n", err),
+                }
+            }
+            Err(err) => println!("Error: {}\n", err),
+        }
+    }
+
     println!("=== End of Examples ===");
 }
@@ -0,0 +1,205 @@
+//! Attribute macros that export Rust functions to rustlisp2.
+//!
+//! `#[lisp_fn]` marks a free function and `#[lisp_module("math")]` marks an
+//! impl block whose public associated functions become `math/name`. Both
+//! generate a registration function that binds the functions in an
+//! environment with `define_typed_function`, so arguments and results are
+//! converted with `FromLisp` and `IntoLisp`, and attach each function's doc
+//! comment as its Lisp docstring.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Error, Expr, ExprLit, Ident, ImplItem, ItemFn, ItemImpl, Lit, LitStr, Meta, Signature, Visibility,
+    parse_macro_input,
+};
+
+/// Exports a function to Lisp.
+///
+/// Generates `register_<function>(env: &Rc<Environment>)`, with the same
+/// visibility as the function, which binds it in `env`. The Lisp name is
+/// given with `name = "..."` and defaults to the Rust name with underscores
+/// turned into hyphens.
+///
+/// ```ignore
+/// /// Fetches a URL and returns the response body
+/// #[lisp_fn(name = "http-get")]
+/// fn http_get(url: String) -> Result<String, HttpError> { ... }
+///
+/// register_http_get(interp.env());
+/// ```
+#[proc_macro_attribute]
+pub fn lisp_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"...\"`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let func = parse_macro_input!(item as ItemFn);
+    expand_fn(name, func)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Exports the public associated functions of an impl block to Lisp as a module.
+///
+/// Generates `register_lisp_module(env: &Rc<Environment>)` on the type, which
+/// binds each function in `env` as `module/name`, where `name` is the Rust
+/// name with underscores turned into hyphens. A function can be given a
+/// different name with `#[lisp_fn(name = "...")]`. Methods that take `self`
+/// and functions that are not `pub` are left out.
+///
+/// ```ignore
+/// struct Math;
+///
+/// #[lisp_module("math")]
+/// impl Math {
+///     /// The square root of `x`
+///     pub fn sqrt(x: f64) -> f64 { x.sqrt() }
+/// }
+///
+/// Math::register_lisp_module(interp.env());   // binds math/sqrt
+/// ```
+#[proc_macro_attribute]
+pub fn lisp_module(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let prefix = if attr.is_empty() {
+        None
+    } else {
+        Some(parse_macro_input!(attr as LitStr).value())
+    };
+    let block = parse_macro_input!(item as ItemImpl);
+    expand_module(prefix, block)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_fn(name: Option<String>, func: ItemFn) -> syn::Result<TokenStream2> {
+    check_signature(&func.sig)?;
+    let ident = &func.sig.ident;
+    let vis = &func.vis;
+    let lisp_name = name.unwrap_or_else(|| lisp_name(ident));
+    let register = format_ident!("register_{}", ident.unraw());
+    let define = define(&lisp_name, quote!(#ident), &func.attrs);
+    let doc = format!(" Binds `{}` in `env` as the Lisp procedure `{}`", ident.unraw(), lisp_name);
+
+    Ok(quote! {
+        #func
+
+        #[doc = #doc]
+        #vis fn #register(env: &::std::rc::Rc<::rustlisp2::Environment>) {
+            #define
+        }
+    })
+}
+
+fn expand_module(prefix: Option<String>, mut block: ItemImpl) -> syn::Result<TokenStream2> {
+    let mut defines = Vec::new();
+    for item in &mut block.items {
+        let ImplItem::Fn(func) = item else { continue };
+        // The name override is only meaningful to this macro, so it is removed
+        let name = take_name_override(&mut func.attrs)?;
+        if !matches!(func.vis, Visibility::Public(_)) || func.sig.receiver().is_some() {
+            continue;
+        }
+        check_signature(&func.sig)?;
+
+        let ident = &func.sig.ident;
+        let name = name.unwrap_or_else(|| lisp_name(ident));
+        let lisp_name = match &prefix {
+            Some(prefix) => format!("{}/{}", prefix, name),
+            None => name,
+        };
+        defines.push(define(&lisp_name, quote!(Self::#ident), &func.attrs));
+    }
+
+    let (impl_generics, _, where_clause) = block.generics.split_for_impl();
+    let self_ty = &block.self_ty;
+    let doc = match &prefix {
+        Some(prefix) => format!(" Binds the functions of the Lisp module `{}` in `env`", prefix),
+        None => " Binds the functions of this Lisp module in `env`".to_string(),
+    };
+
+    Ok(quote! {
+        #block
+
+        impl #impl_generics #self_ty #where_clause {
+            #[doc = #doc]
+            pub fn register_lisp_module(env: &::std::rc::Rc<::rustlisp2::Environment>) {
+                #(#defines)*
+            }
+        }
+    })
+}
+
+/// The statements that bind `func` in `env` as `lisp_name`, with its docstring
+fn define(lisp_name: &str, func: TokenStream2, attrs: &[Attribute]) -> TokenStream2 {
+    let set_doc = docstring(attrs).map(|doc| quote!(env.set_doc(#lisp_name, #doc);));
+    quote! {
+        ::rustlisp2::define_typed_function(env, #lisp_name, #func);
+        #set_doc
+    }
+}
+
+/// Generic functions have no single type to wrap, so they cannot be exported
+fn check_signature(sig: &Signature) -> syn::Result<()> {
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new(sig.generics.span(), "Lisp functions cannot be generic"));
+    }
+    if sig.asyncness.is_some() {
+        return Err(Error::new(sig.asyncness.span(), "Lisp functions cannot be async"));
+    }
+    Ok(())
+}
+
+/// The default Lisp name of a Rust function: `string_length` becomes `string-length`
+fn lisp_name(ident: &Ident) -> String {
+    ident.unraw().to_string().replace('_', "-")
+}
+
+/// Removes a `#[lisp_fn(name = "...")]` attribute, returning the name it gives
+fn take_name_override(attrs: &mut Vec<Attribute>) -> syn::Result<Option<String>> {
+    let mut name = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("lisp_fn") {
+            return true;
+        }
+        if let Err(err) = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"`"))
+            }
+        }) {
+            result = Err(err);
+        }
+        false
+    });
+    result.map(|()| name)
+}
+
+/// The text of the `///` comments in `attrs`, without the space after each `///`
+fn docstring(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if let Meta::NameValue(meta) = &attr.meta
+            && meta.path.is_ident("doc")
+            && let Expr::Lit(ExprLit { lit: Lit::Str(text), .. }) = &meta.value
+        {
+            let text = text.value();
+            lines.extend(text.split('\n').map(|line| line.strip_prefix(' ').unwrap_or(line).to_string()));
+        }
+    }
+    let doc = lines.join("\n");
+    let doc = doc.trim();
+    (!doc.is_empty()).then(|| doc.to_string())
+}
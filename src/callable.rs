@@ -0,0 +1,110 @@
+//! Typed handles to Lisp procedures, for Rust code that calls back into Lisp.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use crate::types::{Value, Environment};
+use crate::error::LispError;
+use crate::eval::apply_in;
+use crate::convert::{FromLisp, IntoLisp, conversion_error};
+use crate::printer::print_value;
+
+/// A tuple of Rust values that can be passed to a Lisp procedure as its arguments
+pub trait IntoLispArgs {
+    fn into_lisp_args(self) -> Vec<Rc<Value>>;
+}
+
+macro_rules! lisp_args {
+    ($(($($A:ident $i:tt),*)),*) => {$(
+        impl<$($A: IntoLisp),*> IntoLispArgs for ($($A,)*) {
+            fn into_lisp_args(self) -> Vec<Rc<Value>> {
+                vec![$(self.$i.into_lisp()),*]
+            }
+        }
+    )*};
+}
+
+lisp_args!(
+    (),
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+);
+
+/// A Lisp procedure or lambda that Rust code can keep and call repeatedly,
+/// for example as a callback. `Args` is the tuple of argument types and
+/// `Ret` the type the result is converted to.
+///
+/// ```ignore
+/// let on_event: LispCallable<(String, i64), bool> = interp.callable("on-event")?;
+/// let handled = on_event.call(("click".to_string(), 3))?;
+/// ```
+///
+/// As a parameter of a typed Rust function it accepts any procedure value.
+///
+/// Rust procedures such as `load` or `macroexpand` are given the handle's
+/// environment as the caller's. Handles from `Interpreter::callable` use the
+/// interpreter's global environment; others use an empty one unless given
+/// another with `with_env`.
+pub struct LispCallable<Args, Ret> {
+    func: Rc<Value>,
+    env: Rc<Environment>,
+    marker: PhantomData<fn(Args) -> Ret>,
+}
+
+impl<Args: IntoLispArgs, Ret: FromLisp> LispCallable<Args, Ret> {
+    /// Wraps a procedure or lambda value, failing for anything else
+    pub fn new(func: Rc<Value>) -> Result<Self, LispError> {
+        match &*func {
+            Value::Procedure(_, _) | Value::Lambda(_, _, _) => Ok(LispCallable {
+                func,
+                env: Rc::new(Environment::new()),
+                marker: PhantomData,
+            }),
+            _ => Err(LispError::NotCallable(print_value(&func))),
+        }
+    }
+
+    /// Makes Rust procedures called through this handle see `env` as the caller's environment
+    pub fn with_env(mut self, env: Rc<Environment>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Calls the procedure and converts its result
+    pub fn call(&self, args: Args) -> Result<Ret, LispError> {
+        apply_in(&self.func, &args.into_lisp_args(), &self.env)?.convert()
+    }
+
+    /// The procedure value itself
+    pub fn value(&self) -> &Rc<Value> {
+        &self.func
+    }
+}
+
+impl<Args, Ret> Clone for LispCallable<Args, Ret> {
+    fn clone(&self) -> Self {
+        LispCallable { func: self.func.clone(), env: self.env.clone(), marker: PhantomData }
+    }
+}
+
+impl<Args, Ret> fmt::Debug for LispCallable<Args, Ret> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LispCallable").field(&print_value(&self.func)).finish()
+    }
+}
+
+impl<Args: IntoLispArgs, Ret: FromLisp> FromLisp for LispCallable<Args, Ret> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        LispCallable::new(value.clone()).map_err(|_| conversion_error::<Self>(value, "expected a procedure"))
+    }
+}
+
+impl<Args, Ret> IntoLisp for LispCallable<Args, Ret> {
+    fn into_lisp(self) -> Rc<Value> {
+        self.func
+    }
+}
@@ -0,0 +1,310 @@
+//! Conversions between Lisp values and Rust types.
+//!
+//! `FromLisp` checks the shape of a value and reports what was wrong with it
+//! instead of coercing, and `IntoLisp` builds the matching Lisp value.
+//! Lists map to `Vec` and tuples, association lists of `(key . value)` pairs
+//! map to `HashMap`, and `()` maps to `None`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use num_bigint::BigInt;
+use crate::types::{Value, car, cdr, cons};
+use crate::number::Number;
+use crate::error::LispError;
+use crate::printer::print_value;
+
+/// A Rust type that can be built from a Lisp value
+pub trait FromLisp: Sized {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError>;
+}
+
+/// A Rust type that can be turned into a Lisp value
+pub trait IntoLisp {
+    fn into_lisp(self) -> Rc<Value>;
+}
+
+impl Value {
+    /// Converts this value to a Rust type, e.g. `let n: i64 = value.convert()?`
+    pub fn convert<T: FromLisp>(self: &Rc<Self>) -> Result<T, LispError> {
+        T::from_lisp(self)
+    }
+}
+
+/// The name of `T` without module paths, e.g. `HashMap<String, i64>`
+pub(crate) fn type_name<T>() -> String {
+    short_type_name(std::any::type_name::<T>())
+}
+
+/// A full type name from `std::any::type_name` without its module paths
+pub(crate) fn short_type_name(full: &str) -> String {
+    let mut name = String::new();
+    let mut segment = String::new();
+    for c in full.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            name.push_str(segment.rsplit("::").next().unwrap_or(""));
+            segment.clear();
+            name.push(c);
+        }
+    }
+    name.push_str(segment.rsplit("::").next().unwrap_or(""));
+    name
+}
+
+/// A conversion error for `value`, which could not become a `T`
+pub(crate) fn conversion_error<T>(value: &Rc<Value>, reason: impl Into<String>) -> LispError {
+    LispError::Conversion {
+        target: type_name::<T>(),
+        value: print_value(value),
+        reason: reason.into(),
+    }
+}
+
+/// Collects the elements of a proper list, or explains why `value` is not one
+fn list_elements<T>(value: &Rc<Value>) -> Result<Vec<Rc<Value>>, LispError> {
+    let mut elements = Vec::new();
+    let mut current = value.clone();
+    loop {
+        match &*current {
+            Value::Nil => return Ok(elements),
+            Value::Cons(_, _) => {
+                elements.push(car(&current));
+                current = cdr(&current);
+            }
+            _ => return Err(conversion_error::<T>(value, "expected a proper list")),
+        }
+    }
+}
+
+/// Converts part of a larger value, naming the part if it fails
+fn convert_part<T: FromLisp, Whole>(whole: &Rc<Value>, part: &str, value: &Rc<Value>) -> Result<T, LispError> {
+    T::from_lisp(value).map_err(|err| conversion_error::<Whole>(whole, format!("{}: {}", part, err)))
+}
+
+fn list(items: impl DoubleEndedIterator<Item = Rc<Value>>) -> Rc<Value> {
+    items.rev().fold(Rc::new(Value::Nil), |list, item| cons(item, list))
+}
+
+impl FromLisp for Rc<Value> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoLisp for Rc<Value> {
+    fn into_lisp(self) -> Rc<Value> {
+        self
+    }
+}
+
+impl IntoLisp for Value {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(self)
+    }
+}
+
+impl FromLisp for Number {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::Number(n) => Ok(n.clone()),
+            _ => Err(conversion_error::<Self>(value, "expected a number")),
+        }
+    }
+}
+
+impl IntoLisp for Number {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::Number(self))
+    }
+}
+
+macro_rules! integer_conversions {
+    ($($t:ty),*) => {$(
+        impl FromLisp for $t {
+            fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+                let converted = match &**value {
+                    Value::Number(Number::Integer(n)) => <$t>::try_from(*n).ok(),
+                    Value::Number(Number::Big(n)) => <$t>::try_from(n).ok(),
+                    _ => return Err(conversion_error::<Self>(value, "expected an exact integer")),
+                };
+                converted.ok_or_else(|| conversion_error::<Self>(
+                    value,
+                    format!("out of range {}..={}", <$t>::MIN, <$t>::MAX),
+                ))
+            }
+        }
+
+        impl IntoLisp for $t {
+            fn into_lisp(self) -> Rc<Value> {
+                Rc::new(Value::Number(Number::from_big(BigInt::from(self))))
+            }
+        }
+    )*};
+}
+
+integer_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromLisp for f64 {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::Number(n) => Ok(n.to_f64()),
+            _ => Err(conversion_error::<Self>(value, "expected a number")),
+        }
+    }
+}
+
+impl IntoLisp for f64 {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::Number(Number::Real(self)))
+    }
+}
+
+impl FromLisp for f32 {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        f64::from_lisp(value)
+            .map(|n| n as f32)
+            .map_err(|_| conversion_error::<Self>(value, "expected a number"))
+    }
+}
+
+impl IntoLisp for f32 {
+    fn into_lisp(self) -> Rc<Value> {
+        f64::from(self).into_lisp()
+    }
+}
+
+impl FromLisp for bool {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(conversion_error::<Self>(value, "expected #t or #f")),
+        }
+    }
+}
+
+impl IntoLisp for bool {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::Bool(self))
+    }
+}
+
+impl FromLisp for String {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(conversion_error::<Self>(value, "expected a string")),
+        }
+    }
+}
+
+impl IntoLisp for String {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::String(self))
+    }
+}
+
+impl IntoLisp for &str {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::String(self.to_string()))
+    }
+}
+
+impl IntoLisp for () {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::Nil)
+    }
+}
+
+impl<T: FromLisp> FromLisp for Vec<T> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        list_elements::<Self>(value)?
+            .iter()
+            .enumerate()
+            .map(|(i, element)| convert_part::<T, Self>(value, &format!("element {}", i), element))
+            .collect()
+    }
+}
+
+impl<T: IntoLisp> IntoLisp for Vec<T> {
+    fn into_lisp(self) -> Rc<Value> {
+        list(self.into_iter().map(IntoLisp::into_lisp))
+    }
+}
+
+impl<T: FromLisp> FromLisp for Option<T> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::Nil => Ok(None),
+            _ => T::from_lisp(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoLisp> IntoLisp for Option<T> {
+    fn into_lisp(self) -> Rc<Value> {
+        match self {
+            Some(value) => value.into_lisp(),
+            None => Rc::new(Value::Nil),
+        }
+    }
+}
+
+impl<K: FromLisp + Eq + Hash, V: FromLisp> FromLisp for HashMap<K, V> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        list_elements::<Self>(value)?
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                if !matches!(&**entry, Value::Cons(_, _)) {
+                    let reason = format!("entry {}: expected a (key . value) pair", i);
+                    return Err(conversion_error::<Self>(value, reason));
+                }
+                let key = convert_part::<K, Self>(value, &format!("key of entry {}", i), &car(entry))?;
+                let val = convert_part::<V, Self>(value, &format!("value of entry {}", i), &cdr(entry))?;
+                Ok((key, val))
+            })
+            .collect()
+    }
+}
+
+impl<K: IntoLisp, V: IntoLisp> IntoLisp for HashMap<K, V> {
+    fn into_lisp(self) -> Rc<Value> {
+        let entries: Vec<Rc<Value>> = self
+            .into_iter()
+            .map(|(key, value)| cons(key.into_lisp(), value.into_lisp()))
+            .collect();
+        list(entries.into_iter())
+    }
+}
+
+macro_rules! tuple_conversions {
+    ($(($len:expr; $($t:ident $i:tt),+)),*) => {$(
+        impl<$($t: FromLisp),+> FromLisp for ($($t,)+) {
+            fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+                let elements = list_elements::<Self>(value)?;
+                if elements.len() != $len {
+                    let reason = format!("expected a list of {} elements, got {}", $len, elements.len());
+                    return Err(conversion_error::<Self>(value, reason));
+                }
+                Ok(($(convert_part::<$t, Self>(value, concat!("element ", $i), &elements[$i])?,)+))
+            }
+        }
+
+        impl<$($t: IntoLisp),+> IntoLisp for ($($t,)+) {
+            fn into_lisp(self) -> Rc<Value> {
+                list([$(self.$i.into_lisp()),+].into_iter())
+            }
+        }
+    )*};
+}
+
+tuple_conversions!(
+    (1; A 0),
+    (2; A 0, B 1),
+    (3; A 0, B 1, C 2),
+    (4; A 0, B 1, C 2, D 3),
+    (5; A 0, B 1, C 2, D 3, E 4),
+    (6; A 0, B 1, C 2, D 3, E 4, F 5)
+);
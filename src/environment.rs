@@ -1,164 +1,895 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
 use std::rc::Rc;
-use std::collections::HashMap;
-use std::cell::RefCell;
-use crate::types::{Value, Environment, car, cdr};
-use crate::eval::{eval, eval_list};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::number::Number;
+use crate::types::{Value, Environment, Tail, car, cdr, cons, set_car, set_cdr, is_truthy};
+use crate::eval::{eval, eval_file, eval_body_tail, bind_params, macroexpand, macroexpand_1};
+use crate::error::{LispError, LispResult};
+use crate::printer::print_value;
+
+/// Counter used to make every symbol returned by `gensym` unique
+static GENSYM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Binds `command-line` in `env` to a procedure returning `args`: the
+/// program name followed by its arguments
+pub fn set_command_line(env: &Environment, args: Vec<String>) {
+    env.define("command-line", Value::procedure("command-line", move |call_args, _| {
+        expect_args("command-line", &call_args, 0..=0)?;
+        Ok(string_list(&args))
+    }));
+}
+
+fn string_list(strings: &[String]) -> Rc<Value> {
+    strings
+        .iter()
+        .rev()
+        .fold(Rc::new(Value::Nil), |list, s| cons(Rc::new(Value::String(s.clone())), list))
+}
+
+/// The documentation of whatever `name` is bound to in `env`: the docstring
+/// attached to the binding, or else the leading string of a lambda or macro body
+pub fn documentation(env: &Environment, name: &str) -> Option<String> {
+    if let Some(doc) = env.doc(name) {
+        return Some(doc);
+    }
+    match &*env.get(name)? {
+        Value::Lambda(_, body, _) | Value::Macro(_, body, _) => match &*car(body) {
+            Value::String(doc) if matches!(&*cdr(body), Value::Cons(_, _)) => Some(doc.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
 pub fn setup_environment() -> Rc<Environment> {
-    let env = Rc::new(RefCell::new(HashMap::new()));
-    
+    let env = Rc::new(Environment::new());
+
     // Add primitives
-    env.borrow_mut().insert("nil".to_string(), Rc::new(Value::Nil));
-    env.borrow_mut().insert("#t".to_string(), Rc::new(Value::Bool(true)));
-    
-    // Add primitive procedures
-    env.borrow_mut().insert("quote".to_string(), 
-        Rc::new(Value::Procedure("quote".to_string(), |args, _| car(&args))));
-    
-    env.borrow_mut().insert("+".to_string(), 
-        Rc::new(Value::Procedure("+".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            let mut result = 0.0;
-            let mut current = args;
-            while let Value::Cons(car, cdr) = &*current {
-                if let Value::Number(n) = &**car {
-                    result += n;
+    env.define("nil", Rc::new(Value::Nil));
+    env.define("#t", Rc::new(Value::Bool(true)));
+    env.define("#f", Rc::new(Value::Bool(false)));
+
+    // Add special forms, which receive their arguments unevaluated
+    env.define("quote",
+        Value::special_form("quote", |args, _| Ok(Tail::Return(car(&args)))));
+
+    env.define("define",
+        Value::special_form("define", |args, env| {
+            match &*car(&args) {
+                Value::Symbol(name) => {
+                    let value = eval(car(&cdr(&args)), env.clone())?;
+                    env.define(name, value.clone());
+                    Ok(Tail::Return(value))
                 }
-                current = cdr.clone();
+                _ => Err(type_mismatch("symbol", &car(&args))),
             }
-            Rc::new(Value::Number(result))
-        })));
-    
-    env.borrow_mut().insert("define".to_string(), 
-        Rc::new(Value::Procedure("define".to_string(), |args, env| {
-            if let Value::Symbol(name) = &*car(&args) {
-                let value = eval(car(&cdr(&args)), env.clone());
-                env.borrow_mut().insert(name.clone(), value.clone());
-                return value;
-            }
-            Rc::new(Value::Nil)
-        })));
-    
-    env.borrow_mut().insert("lambda".to_string(), 
-        Rc::new(Value::Procedure("lambda".to_string(), |args, env| {
-            Rc::new(Value::Lambda(
+        }));
+
+    env.define("set!",
+        Value::special_form("set!", |args, env| {
+            match &*car(&args) {
+                Value::Symbol(name) => {
+                    let value = eval(car(&cdr(&args)), env.clone())?;
+                    env.set(name, value.clone())?;
+                    Ok(Tail::Return(value))
+                }
+                _ => Err(type_mismatch("symbol", &car(&args))),
+            }
+        }));
+
+    env.define("lambda",
+        Value::special_form("lambda", |args, env| {
+            Ok(Tail::Return(Value::new_lambda(
                 car(&args),
-                car(&cdr(&args)),
+                cdr(&args),
                 env.clone(),
-            ))
-        })));
-    
-    // Add more primitive procedures
-    env.borrow_mut().insert("-".to_string(), 
-        Rc::new(Value::Procedure("-".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            if let Value::Cons(first, rest) = &*args {
-                if let Value::Number(n) = &**first {
-                    let mut result = *n;
-                    let mut current = rest.clone();
-                    while let Value::Cons(car, cdr) = &*current {
-                        if let Value::Number(n) = &**car {
-                            result -= n;
-                        }
-                        current = cdr.clone();
-                    }
-                    return Rc::new(Value::Number(result));
+            )))
+        }));
+
+    // Macros receive their arguments as unevaluated forms and return the
+    // form to evaluate in place of the call
+    env.define("defmacro",
+        Value::special_form("defmacro", |args, env| {
+            // (defmacro name params body...)
+            match &*car(&args) {
+                Value::Symbol(name) => {
+                    let value = Value::new_macro(car(&cdr(&args)), cdr(&cdr(&args)), env.clone());
+                    env.define(name, value.clone());
+                    Ok(Tail::Return(value))
                 }
+                _ => Err(type_mismatch("symbol", &car(&args))),
             }
-            Rc::new(Value::Number(0.0))
-        })));
-    
-    env.borrow_mut().insert("*".to_string(), 
-        Rc::new(Value::Procedure("*".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            let mut result = 1.0;
-            let mut current = args;
-            while let Value::Cons(car, cdr) = &*current {
-                if let Value::Number(n) = &**car {
-                    result *= n;
-                }
-                current = cdr.clone();
-            }
-            Rc::new(Value::Number(result))
-        })));
-    
-    env.borrow_mut().insert("/".to_string(), 
-        Rc::new(Value::Procedure("/".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            if let Value::Cons(first, rest) = &*args {
-                if let Value::Number(n) = &**first {
-                    let mut result = *n;
-                    let mut current = rest.clone();
-                    while let Value::Cons(car, cdr) = &*current {
-                        if let Value::Number(n) = &**car {
-                            if *n != 0.0 {
-                                result /= n;
-                            }
-                        }
-                        current = cdr.clone();
-                    }
-                    return Rc::new(Value::Number(result));
+        }));
+
+    env.define("define-macro",
+        Value::special_form("define-macro", |args, env| {
+            // (define-macro (name . params) body...)
+            let signature = car(&args);
+            match &*car(&signature) {
+                Value::Symbol(name) => {
+                    let value = Value::new_macro(cdr(&signature), cdr(&args), env.clone());
+                    env.define(name, value.clone());
+                    Ok(Tail::Return(value))
                 }
+                _ => Err(type_mismatch("(name . params)", &signature)),
+            }
+        }));
+
+    env.define("quasiquote",
+        Value::special_form("quasiquote", |args, env| {
+            Ok(Tail::Return(quasiquote(car(&args), 1, &env)?))
+        }));
+
+    env.define("macroexpand-1",
+        Value::procedure("macroexpand-1", |args, env| {
+            let args = expect_args("macroexpand-1", &args, 1..=1)?;
+            Ok(macroexpand_1(args[0].clone(), &env)?.0)
+        }));
+
+    env.define("macroexpand",
+        Value::procedure("macroexpand", |args, env| {
+            let args = expect_args("macroexpand", &args, 1..=1)?;
+            macroexpand(args[0].clone(), &env)
+        }));
+
+    env.define("gensym",
+        Value::procedure("gensym", |args, _| {
+            // Fresh symbols let macros introduce bindings that cannot capture user names
+            let args = expect_args("gensym", &args, 0..=1)?;
+            let prefix = match args.first() {
+                Some(prefix) => string_arg(prefix)?,
+                None => "g".to_string(),
+            };
+            let n = GENSYM_COUNTER.fetch_add(1, Ordering::Relaxed);
+            Ok(Rc::new(Value::Symbol(format!("#:{}{}", prefix, n))))
+        }));
+
+    env.define("load",
+        Value::procedure("load", |args, env| {
+            // Definitions in a loaded file are global, wherever `load` is called from
+            let args = expect_args("load", &args, 1..=1)?;
+            let mut global = env;
+            while let Some(parent) = global.parent().cloned() {
+                global = parent;
             }
-            Rc::new(Value::Number(0.0))
-        })));
-    
-    env.borrow_mut().insert("=".to_string(), 
-        Rc::new(Value::Procedure("=".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            if let Value::Cons(first, rest) = &*args {
-                if let Value::Number(n1) = &**first {
-                    if let Value::Cons(second, _) = &**rest {
-                        if let Value::Number(n2) = &**second {
-                            return if n1 == n2 {
-                                Rc::new(Value::Bool(true))
-                            } else {
-                                Rc::new(Value::Bool(false))
-                            };
+            eval_file(&string_arg(&args[0])?, global)
+        }));
+
+    // Scripts and interpreters replace this with their own arguments
+    set_command_line(&env, Vec::new());
+
+    env.define("display",
+        Value::procedure("display", |args, _| {
+            // Strings are written without quotes or escapes
+            let args = expect_args("display", &args, 1..=1)?;
+            match &*args[0] {
+                Value::String(s) => print!("{}", s),
+                _ => print!("{}", print_value(&args[0])),
+            }
+            io::stdout().flush().ok();
+            Ok(Rc::new(Value::Nil))
+        }));
+
+    env.define("newline",
+        Value::procedure("newline", |args, _| {
+            expect_args("newline", &args, 0..=0)?;
+            println!();
+            Ok(Rc::new(Value::Nil))
+        }));
+
+    // Conditionals and sequencing evaluate only the forms they select,
+    // leaving the selected form in tail position
+    env.define("if",
+        Value::special_form("if", |args, env| {
+            let len = list_length(&args);
+            if !(2..=3).contains(&len) {
+                return Err(arity_mismatch("if", "2 or 3", len));
+            }
+            if is_truthy(&eval(car(&args), env.clone())?) {
+                Ok(Tail::Eval(car(&cdr(&args)), env))
+            } else {
+                eval_body_tail(cdr(&cdr(&args)), env)
+            }
+        }));
+
+    env.define("cond",
+        Value::special_form("cond", |args, env| {
+            let mut clauses = args;
+            while let Value::Cons(_, _) = &*clauses {
+                let clause = car(&clauses);
+                if !matches!(&*clause, Value::Cons(_, _)) {
+                    return Err(type_mismatch("cond clause", &clause));
+                }
+                let test = car(&clause);
+                let body = cdr(&clause);
+                if is_symbol(&test, "else") {
+                    return eval_body_tail(body, env);
+                }
+                let value = eval(test, env.clone())?;
+                if is_truthy(&value) {
+                    return match &*body {
+                        Value::Nil => Ok(Tail::Return(value)),
+                        Value::Cons(_, _) if is_symbol(&car(&body), "=>") => {
+                            // (test => receiver) calls receiver with the value of test
+                            let nil = Rc::new(Value::Nil);
+                            let quoted = cons(Rc::new(Value::Symbol("quote".to_string())), cons(value, nil.clone()));
+                            Ok(Tail::Eval(cons(car(&cdr(&body)), cons(quoted, nil)), env))
                         }
-                    }
+                        _ => eval_body_tail(body, env),
+                    };
                 }
+                clauses = cdr(&clauses);
             }
-            Rc::new(Value::Bool(false))
-        })));
-    
-    env.borrow_mut().insert("<".to_string(), 
-        Rc::new(Value::Procedure("<".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            if let Value::Cons(first, rest) = &*args {
-                if let Value::Number(n1) = &**first {
-                    if let Value::Cons(second, _) = &**rest {
-                        if let Value::Number(n2) = &**second {
-                            return if n1 < n2 {
-                                Rc::new(Value::Bool(true))
-                            } else {
-                                Rc::new(Value::Bool(false))
-                            };
-                        }
-                    }
+            Ok(Tail::Return(Rc::new(Value::Nil)))
+        }));
+
+    env.define("when",
+        Value::special_form("when", |args, env| {
+            if is_truthy(&eval(car(&args), env.clone())?) {
+                eval_body_tail(cdr(&args), env)
+            } else {
+                Ok(Tail::Return(Rc::new(Value::Nil)))
+            }
+        }));
+
+    env.define("unless",
+        Value::special_form("unless", |args, env| {
+            if is_truthy(&eval(car(&args), env.clone())?) {
+                Ok(Tail::Return(Rc::new(Value::Nil)))
+            } else {
+                eval_body_tail(cdr(&args), env)
+            }
+        }));
+
+    env.define("begin",
+        Value::special_form("begin", eval_body_tail));
+
+    // Local bindings, each evaluating its body in tail position in a new frame
+    env.define("let",
+        Value::special_form("let", |args, env| {
+            if let Value::Symbol(name) = &*car(&args) {
+                // Named let: (let loop ((var init) ...) body...) binds `loop` to a
+                // procedure over the variables and calls it with the inits
+                let bindings = parse_bindings("let", &cdr(&args))?;
+                check_distinct("let", &bindings)?;
+                let loop_env = Rc::new(Environment::with_parent(env.clone()));
+                let params = bindings.iter().rev().fold(Rc::new(Value::Nil), |list, (var, _)| {
+                    cons(Rc::new(Value::Symbol(var.clone())), list)
+                });
+                let body = cdr(&cdr(&args));
+                loop_env.define(name, Value::new_lambda(params.clone(), body.clone(), loop_env.clone()));
+
+                let mut values = Rc::new(Value::Nil);
+                for (_, init) in bindings.into_iter().rev() {
+                    values = cons(eval(init, env.clone())?, values);
                 }
+                let call_env = Rc::new(Environment::with_parent(loop_env));
+                bind_params(params, values, call_env.clone());
+                return eval_body_tail(body, call_env);
             }
-            Rc::new(Value::Bool(false))
-        })));
-    
-    env.borrow_mut().insert(">".to_string(), 
-        Rc::new(Value::Procedure(">".to_string(), |args, env| {
-            let args = eval_list(args, env);
-            if let Value::Cons(first, rest) = &*args {
-                if let Value::Number(n1) = &**first {
-                    if let Value::Cons(second, _) = &**rest {
-                        if let Value::Number(n2) = &**second {
-                            return if n1 > n2 {
-                                Rc::new(Value::Bool(true))
-                            } else {
-                                Rc::new(Value::Bool(false))
-                            };
-                        }
+
+            let bindings = parse_bindings("let", &args)?;
+            check_distinct("let", &bindings)?;
+            let let_env = Rc::new(Environment::with_parent(env.clone()));
+            for (name, init) in bindings {
+                let_env.define(&name, eval(init, env.clone())?);
+            }
+            eval_body_tail(cdr(&args), let_env)
+        }));
+
+    env.define("let*",
+        Value::special_form("let*", |args, env| {
+            // Each binding gets its own frame, so later inits see earlier variables
+            let mut let_env = env;
+            for (name, init) in parse_bindings("let*", &args)? {
+                let value = eval(init, let_env.clone())?;
+                let_env = Rc::new(Environment::with_parent(let_env));
+                let_env.define(&name, value);
+            }
+            eval_body_tail(cdr(&args), Rc::new(Environment::with_parent(let_env)))
+        }));
+
+    env.define("letrec",
+        Value::special_form("letrec", |args, env| {
+            // Every variable is bound, but unassigned, while the inits are
+            // evaluated, and none is assigned until they all have been
+            let bindings = parse_bindings("letrec", &args)?;
+            check_distinct("letrec", &bindings)?;
+            let let_env = unassigned_frame(&bindings, env);
+            let mut values = Vec::new();
+            for (name, init) in bindings {
+                values.push((name, eval(init, let_env.clone())?));
+            }
+            for (name, value) in values {
+                let_env.define(&name, value);
+            }
+            eval_body_tail(cdr(&args), let_env)
+        }));
+
+    env.define("letrec*",
+        Value::special_form("letrec*", |args, env| {
+            // Each variable is assigned as soon as its init has been evaluated
+            let bindings = parse_bindings("letrec*", &args)?;
+            check_distinct("letrec*", &bindings)?;
+            let let_env = unassigned_frame(&bindings, env);
+            for (name, init) in bindings {
+                let value = eval(init, let_env.clone())?;
+                let_env.define(&name, value);
+            }
+            eval_body_tail(cdr(&args), let_env)
+        }));
+
+    // Add primitive procedures, which receive their arguments evaluated
+    env.define("+",
+        Value::procedure("+", |args, _| {
+            let numbers = number_args(&args)?;
+            Ok(number(numbers.iter().fold(Number::Integer(0), |acc, n| &acc + n)))
+        }));
+
+    env.define("-",
+        Value::procedure("-", |args, _| {
+            let numbers = number_args(&args)?;
+            match numbers.as_slice() {
+                [] => Err(arity_mismatch("-", "at least 1", 0)),
+                [n] => Ok(number(-n)),
+                [first, rest @ ..] => Ok(number(rest.iter().fold(first.clone(), |acc, n| &acc - n))),
+            }
+        }));
+
+    env.define("*",
+        Value::procedure("*", |args, _| {
+            let numbers = number_args(&args)?;
+            Ok(number(numbers.iter().fold(Number::Integer(1), |acc, n| &acc * n)))
+        }));
+
+    env.define("/",
+        Value::procedure("/", |args, _| {
+            let numbers = number_args(&args)?;
+            let (first, divisors) = match numbers.as_slice() {
+                [] => return Err(arity_mismatch("/", "at least 1", 0)),
+                [_] => (Number::Integer(1), &numbers[..]),
+                [first, rest @ ..] => (first.clone(), rest),
+            };
+            Ok(number(divisors.iter().try_fold(first, |acc, n| acc.divide(n))?))
+        }));
+
+    env.define("quotient",
+        Value::procedure("quotient", |args, _| {
+            let [a, b] = number_pair("quotient", &args)?;
+            Ok(number(a.quotient(&b)?))
+        }));
+
+    env.define("remainder",
+        Value::procedure("remainder", |args, _| {
+            let [a, b] = number_pair("remainder", &args)?;
+            Ok(number(a.remainder(&b)?))
+        }));
+
+    env.define("modulo",
+        Value::procedure("modulo", |args, _| {
+            let [a, b] = number_pair("modulo", &args)?;
+            Ok(number(a.modulo(&b)?))
+        }));
+
+    env.define("expt",
+        Value::procedure("expt", |args, _| {
+            let [base, exponent] = number_pair("expt", &args)?;
+            Ok(number(base.expt(&exponent)?))
+        }));
+
+    env.define("exact->inexact",
+        Value::procedure("exact->inexact", |args, _| {
+            let numbers = expect_numbers("exact->inexact", &args, 1..=1)?;
+            Ok(number(numbers[0].to_inexact()))
+        }));
+
+    env.define("inexact->exact",
+        Value::procedure("inexact->exact", |args, _| {
+            let numbers = expect_numbers("inexact->exact", &args, 1..=1)?;
+            Ok(number(numbers[0].to_exact()?))
+        }));
+
+    env.define("number?",
+        Value::procedure("number?", |args, _| {
+            let args = expect_args("number?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Number(_)))))
+        }));
+
+    env.define("integer?",
+        Value::procedure("integer?", |args, _| {
+            let args = expect_args("integer?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Number(n) if n.is_integer()))))
+        }));
+
+    env.define("exact?",
+        Value::procedure("exact?", |args, _| {
+            let numbers = expect_numbers("exact?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(numbers[0].is_exact())))
+        }));
+
+    env.define("inexact?",
+        Value::procedure("inexact?", |args, _| {
+            let numbers = expect_numbers("inexact?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(!numbers[0].is_exact())))
+        }));
+
+    env.define("=",
+        Value::procedure("=", |args, _| {
+            compare("=", &args, |a, b| a == b)
+        }));
+
+    env.define("<",
+        Value::procedure("<", |args, _| {
+            compare("<", &args, |a, b| a < b)
+        }));
+
+    env.define(">",
+        Value::procedure(">", |args, _| {
+            compare(">", &args, |a, b| a > b)
+        }));
+
+    env.define("<=",
+        Value::procedure("<=", |args, _| {
+            compare("<=", &args, |a, b| a <= b)
+        }));
+
+    env.define(">=",
+        Value::procedure(">=", |args, _| {
+            compare(">=", &args, |a, b| a >= b)
+        }));
+
+    // Pair and list procedures
+    env.define("cons",
+        Value::procedure("cons", |args, _| {
+            let args = expect_args("cons", &args, 2..=2)?;
+            Ok(cons(args[0].clone(), args[1].clone()))
+        }));
+
+    env.define("car",
+        Value::procedure("car", |args, _| {
+            let args = expect_args("car", &args, 1..=1)?;
+            Ok(car(&pair_arg(&args[0])?))
+        }));
+
+    env.define("cdr",
+        Value::procedure("cdr", |args, _| {
+            let args = expect_args("cdr", &args, 1..=1)?;
+            Ok(cdr(&pair_arg(&args[0])?))
+        }));
+
+    env.define("set-car!",
+        Value::procedure("set-car!", |args, _| {
+            let args = expect_args("set-car!", &args, 2..=2)?;
+            set_car(&pair_arg(&args[0])?, args[1].clone());
+            Ok(Rc::new(Value::Nil))
+        }));
+
+    env.define("set-cdr!",
+        Value::procedure("set-cdr!", |args, _| {
+            let args = expect_args("set-cdr!", &args, 2..=2)?;
+            set_cdr(&pair_arg(&args[0])?, args[1].clone());
+            Ok(Rc::new(Value::Nil))
+        }));
+
+    env.define("list",
+        Value::procedure("list", |args, _| Ok(args)));
+
+    env.define("pair?",
+        Value::procedure("pair?", |args, _| {
+            let args = expect_args("pair?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Cons(_, _)))))
+        }));
+
+    env.define("null?",
+        Value::procedure("null?", |args, _| {
+            let args = expect_args("null?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Nil))))
+        }));
+
+    env.define("procedure?",
+        Value::procedure("procedure?", |args, _| {
+            let args = expect_args("procedure?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Procedure(_, _) | Value::Lambda(_, _, _)))))
+        }));
+
+    env.define("equal?",
+        Value::procedure("equal?", |args, _| {
+            let args = expect_args("equal?", &args, 2..=2)?;
+            Ok(Rc::new(Value::Bool(equal(&args[0], &args[1]))))
+        }));
+
+    // Rust values held by scripts
+    env.define("foreign?",
+        Value::procedure("foreign?", |args, _| {
+            let args = expect_args("foreign?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::Foreign(_)))))
+        }));
+
+    env.define("send",
+        Value::procedure("send", |args, env| {
+            // (send obj 'method args...) calls the method with (obj args...)
+            let object = car(&args);
+            let Value::Foreign(foreign) = &*object else {
+                return Err(LispError::TypeMismatch {
+                    expected: "foreign value".to_string(),
+                    got: print_value(&object),
+                });
+            };
+            let method_name = car(&cdr(&args));
+            let Value::Symbol(name) = &*method_name else {
+                return Err(LispError::TypeMismatch {
+                    expected: "method name".to_string(),
+                    got: print_value(&method_name),
+                });
+            };
+            let method = env.method(foreign.rust_type_id(), name).ok_or_else(|| {
+                LispError::Custom(format!("{} has no method '{}'", foreign.type_name(), name))
+            })?;
+            method(cons(object.clone(), cdr(&cdr(&args))), env)
+        }));
+
+    env.define("documentation",
+        Value::procedure("documentation", |args, env| {
+            let args = expect_args("documentation", &args, 1..=1)?;
+            let Value::Symbol(name) = &*args[0] else {
+                return Err(LispError::TypeMismatch {
+                    expected: "symbol".to_string(),
+                    got: print_value(&args[0]),
+                });
+            };
+            Ok(Rc::new(documentation(&env, name).map_or(Value::Nil, Value::String)))
+        }));
+
+    // String procedures
+    env.define("string?",
+        Value::procedure("string?", |args, _| {
+            let args = expect_args("string?", &args, 1..=1)?;
+            Ok(Rc::new(Value::Bool(matches!(&*args[0], Value::String(_)))))
+        }));
+
+    env.define("string-length",
+        Value::procedure("string-length", |args, _| {
+            let args = expect_args("string-length", &args, 1..=1)?;
+            let s = string_arg(&args[0])?;
+            Ok(number(Number::Integer(s.chars().count() as i64)))
+        }));
+
+    env.define("string-append",
+        Value::procedure("string-append", |args, _| {
+            let mut result = String::new();
+            let mut current = args;
+            while let Value::Cons(_, _) = &*current {
+                result.push_str(&string_arg(&car(&current))?);
+                current = cdr(&current);
+            }
+            Ok(Rc::new(Value::String(result)))
+        }));
+
+    env.define("substring",
+        Value::procedure("substring", |args, _| {
+            let args = expect_args("substring", &args, 2..=3)?;
+            let chars: Vec<char> = string_arg(&args[0])?.chars().collect();
+            let start = index_arg(&args[1])?;
+            let end = match args.get(2) {
+                Some(end) => index_arg(end)?,
+                None => chars.len(),
+            };
+            if start > end || end > chars.len() {
+                return Err(LispError::Custom(format!(
+                    "substring: range {}..{} out of bounds for string of length {}",
+                    start, end, chars.len()
+                )));
+            }
+            Ok(Rc::new(Value::String(chars[start..end].iter().collect())))
+        }));
+
+    env.define("string-split",
+        Value::procedure("string-split", |args, _| {
+            let args = expect_args("string-split", &args, 1..=2)?;
+            let s = string_arg(&args[0])?;
+            let parts: Vec<String> = match args.get(1) {
+                Some(separator) => {
+                    let separator = string_arg(separator)?;
+                    if separator.is_empty() {
+                        return Err(LispError::Custom("string-split: empty separator".to_string()));
                     }
+                    s.split(separator.as_str()).map(str::to_string).collect()
                 }
+                None => s.split_whitespace().map(str::to_string).collect(),
+            };
+            Ok(parts.into_iter().rev().fold(Rc::new(Value::Nil), |list, part| {
+                cons(Rc::new(Value::String(part)), list)
+            }))
+        }));
+
+    env.define("string=?",
+        Value::procedure("string=?", |args, _| {
+            let args = expect_args("string=?", &args, 2..=2)?;
+            Ok(Rc::new(Value::Bool(string_arg(&args[0])? == string_arg(&args[1])?)))
+        }));
+
+    env.define("string->number",
+        Value::procedure("string->number", |args, _| {
+            let args = expect_args("string->number", &args, 1..=1)?;
+            match Number::parse(string_arg(&args[0])?.trim()) {
+                Some(n) => Ok(number(n)),
+                None => Ok(Rc::new(Value::Bool(false))),
+            }
+        }));
+
+    env.define("number->string",
+        Value::procedure("number->string", |args, _| {
+            let args = expect_args("number->string", &args, 1..=1)?;
+            match &*args[0] {
+                Value::Number(_) => Ok(Rc::new(Value::String(print_value(&args[0])))),
+                _ => Err(type_mismatch("number", &args[0])),
             }
-            Rc::new(Value::Bool(false))
-        })));
-    
+        }));
+
+    env.define("string->symbol",
+        Value::procedure("string->symbol", |args, _| {
+            let args = expect_args("string->symbol", &args, 1..=1)?;
+            Ok(Rc::new(Value::Symbol(string_arg(&args[0])?)))
+        }));
+
+    env.define("symbol->string",
+        Value::procedure("symbol->string", |args, _| {
+            let args = expect_args("symbol->string", &args, 1..=1)?;
+            match &*args[0] {
+                Value::Symbol(s) => Ok(Rc::new(Value::String(s.clone()))),
+                _ => Err(type_mismatch("symbol", &args[0])),
+            }
+        }));
+
     env
 }
+
+/// Structural equality, as tested by `equal?`. Numbers must also agree in
+/// exactness, procedures are only equal to themselves, and foreign values
+/// use the equality registered for their type. Circular structures compare
+/// equal when they unfold to the same infinite tree.
+fn equal(a: &Rc<Value>, b: &Rc<Value>) -> bool {
+    equal_pairs(a, b, &mut HashSet::new())
+}
+
+/// `equal`, where `assumed` holds the pairs of pairs already being compared.
+/// Meeting one of them again means both sides have looped back in step, so
+/// that comparison is left to the first visit to decide.
+fn equal_pairs(a: &Rc<Value>, b: &Rc<Value>, assumed: &mut HashSet<(*const Value, *const Value)>) -> bool {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    loop {
+        if Rc::ptr_eq(&a, &b) {
+            return true;
+        }
+        match (&*a, &*b) {
+            (Value::Cons(_, _), Value::Cons(_, _)) => {
+                if !assumed.insert((Rc::as_ptr(&a), Rc::as_ptr(&b))) {
+                    return true;
+                }
+                if !equal_pairs(&car(&a), &car(&b), assumed) {
+                    return false;
+                }
+                // Walk down the spine in a loop so long lists do not recurse
+                let (next_a, next_b) = (cdr(&a), cdr(&b));
+                a = next_a;
+                b = next_b;
+            }
+            (Value::Nil, Value::Nil) => return true,
+            (Value::Bool(x), Value::Bool(y)) => return x == y,
+            (Value::Number(x), Value::Number(y)) => return x.is_exact() == y.is_exact() && x == y,
+            (Value::Symbol(x), Value::Symbol(y)) | (Value::String(x), Value::String(y)) => return x == y,
+            (Value::Foreign(x), Value::Foreign(y)) => return x == y,
+            _ => return false,
+        }
+    }
+}
+
+/// Builds the value of a quasiquoted template, evaluating `unquote` forms at
+/// nesting depth 1 and splicing the lists produced by `unquote-splicing`
+fn quasiquote(template: Rc<Value>, depth: usize, env: &Rc<Environment>) -> LispResult {
+    if !matches!(&*template, Value::Cons(_, _)) {
+        return Ok(template);
+    }
+    let head = car(&template);
+    let operand = car(&cdr(&template));
+    if is_symbol(&head, "unquote") {
+        return if depth == 1 {
+            eval(operand, env.clone())
+        } else {
+            Ok(list2(head, quasiquote(operand, depth - 1, env)?))
+        };
+    }
+    if is_symbol(&head, "quasiquote") {
+        return Ok(list2(head, quasiquote(operand, depth + 1, env)?));
+    }
+
+    let mut items = Vec::new();
+    let mut current = template.clone();
+    let tail = loop {
+        if !matches!(&*current, Value::Cons(_, _)) {
+            break current.clone();
+        }
+        let item = car(&current);
+        // A dotted tail written as `(a . ,b)` reads as `(a unquote b)`
+        if is_symbol(&item, "unquote") {
+            break quasiquote(current.clone(), depth, env)?;
+        }
+        if depth == 1 && is_symbol(&car(&item), "unquote-splicing") {
+            let mut list = eval(car(&cdr(&item)), env.clone())?;
+            while let Value::Cons(_, _) = &*list {
+                items.push(car(&list));
+                list = cdr(&list);
+            }
+        } else {
+            items.push(quasiquote(item, depth, env)?);
+        }
+        current = cdr(&current);
+    };
+    Ok(items.into_iter().rev().fold(tail, |list, item| cons(item, list)))
+}
+
+/// Parses the binding list of the form `((name init) ...)` that heads `args`,
+/// the operands of a binding form after any name
+fn parse_bindings(form: &str, args: &Rc<Value>) -> Result<Vec<(String, Rc<Value>)>, LispError> {
+    if !matches!(&**args, Value::Cons(_, _)) {
+        return Err(LispError::Syntax(format!("{}: missing binding list", form)));
+    }
+    let bindings = &car(args);
+    let mut result = Vec::new();
+    let mut current = bindings.clone();
+    while let Value::Cons(_, _) = &*current {
+        let binding = car(&current);
+        match (&*car(&binding), list_length(&binding)) {
+            (Value::Symbol(name), 2) => result.push((name.clone(), car(&cdr(&binding)))),
+            _ => return Err(type_mismatch(&format!("{} binding (name value)", form), &binding)),
+        }
+        current = cdr(&current);
+    }
+    if !matches!(&*current, Value::Nil) {
+        return Err(type_mismatch(&format!("{} binding list", form), bindings));
+    }
+    Ok(result)
+}
+
+/// Rejects a binding list that binds the same name twice, which only
+/// `let*` allows
+fn check_distinct(form: &str, bindings: &[(String, Rc<Value>)]) -> Result<(), LispError> {
+    let mut seen = HashSet::new();
+    for (name, _) in bindings {
+        if !seen.insert(name.as_str()) {
+            return Err(LispError::Syntax(format!("{}: duplicate variable {}", form, name)));
+        }
+    }
+    Ok(())
+}
+
+/// A frame inside `env` in which every name in `bindings` is bound but
+/// unassigned, so that a reference to one of them is an error rather than
+/// a reference to an enclosing binding of the same name
+fn unassigned_frame(bindings: &[(String, Rc<Value>)], env: Rc<Environment>) -> Rc<Environment> {
+    let frame = Rc::new(Environment::with_parent(env));
+    for (name, _) in bindings {
+        frame.define(name, Rc::new(Value::Unassigned));
+    }
+    frame
+}
+
+fn list2(first: Rc<Value>, second: Rc<Value>) -> Rc<Value> {
+    cons(first, cons(second, Rc::new(Value::Nil)))
+}
+
+/// Collects an evaluated argument list, checking that its length is in `arity`
+fn expect_args(name: &str, args: &Rc<Value>, arity: RangeInclusive<usize>) -> Result<Vec<Rc<Value>>, LispError> {
+    let mut result = Vec::new();
+    let mut current = args.clone();
+    while let Value::Cons(_, _) = &*current {
+        result.push(car(&current));
+        current = cdr(&current);
+    }
+    if !arity.contains(&result.len()) {
+        let expected = if arity.start() == arity.end() {
+            arity.start().to_string()
+        } else {
+            format!("{} to {}", arity.start(), arity.end())
+        };
+        return Err(arity_mismatch(name, &expected, result.len()));
+    }
+    Ok(result)
+}
+
+fn pair_arg(value: &Rc<Value>) -> Result<Rc<Value>, LispError> {
+    match &**value {
+        Value::Cons(_, _) => Ok(value.clone()),
+        _ => Err(type_mismatch("pair", value)),
+    }
+}
+
+fn string_arg(value: &Rc<Value>) -> Result<String, LispError> {
+    match &**value {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(type_mismatch("string", value)),
+    }
+}
+
+fn index_arg(value: &Rc<Value>) -> Result<usize, LispError> {
+    match &**value {
+        Value::Number(n) => n.to_usize().ok_or_else(|| type_mismatch("non-negative exact integer", value)),
+        _ => Err(type_mismatch("non-negative exact integer", value)),
+    }
+}
+
+/// Requires every argument in an evaluated argument list to be a number
+fn number_args(args: &Rc<Value>) -> Result<Vec<Number>, LispError> {
+    let mut numbers = Vec::new();
+    let mut current = args.clone();
+    while let Value::Cons(_, _) = &*current {
+        let arg = car(&current);
+        match &*arg {
+            Value::Number(n) => numbers.push(n.clone()),
+            _ => return Err(type_mismatch("number", &arg)),
+        }
+        current = cdr(&current);
+    }
+    Ok(numbers)
+}
+
+/// Applies a numeric comparison to each adjacent pair of arguments
+fn compare(name: &str, args: &Rc<Value>, op: fn(&Number, &Number) -> bool) -> LispResult {
+    let numbers = number_args(args)?;
+    if numbers.len() < 2 {
+        return Err(arity_mismatch(name, "at least 2", numbers.len()));
+    }
+    Ok(Rc::new(Value::Bool(numbers.windows(2).all(|pair| op(&pair[0], &pair[1])))))
+}
+
+/// Collects an evaluated argument list of numbers, checking that its length is in `arity`
+fn expect_numbers(name: &str, args: &Rc<Value>, arity: RangeInclusive<usize>) -> Result<Vec<Number>, LispError> {
+    expect_args(name, args, arity)?
+        .iter()
+        .map(|arg| match &**arg {
+            Value::Number(n) => Ok(n.clone()),
+            _ => Err(type_mismatch("number", arg)),
+        })
+        .collect()
+}
+
+/// Requires exactly two numeric arguments
+fn number_pair(name: &str, args: &Rc<Value>) -> Result<[Number; 2], LispError> {
+    let numbers = expect_numbers(name, args, 2..=2)?;
+    Ok([numbers[0].clone(), numbers[1].clone()])
+}
+
+fn number(n: Number) -> Rc<Value> {
+    Rc::new(Value::Number(n))
+}
+
+fn is_symbol(value: &Rc<Value>, name: &str) -> bool {
+    matches!(&**value, Value::Symbol(s) if s == name)
+}
+
+fn list_length(list: &Rc<Value>) -> usize {
+    let mut len = 0;
+    let mut current = list.clone();
+    while let Value::Cons(_, _) = &*current {
+        len += 1;
+        current = cdr(&current);
+    }
+    len
+}
+
+fn type_mismatch(expected: &str, got: &Rc<Value>) -> LispError {
+    LispError::TypeMismatch {
+        expected: expected.to_string(),
+        got: print_value(got),
+    }
+}
+
+fn arity_mismatch(name: &str, expected: &str, got: usize) -> LispError {
+    LispError::ArityMismatch {
+        name: name.to_string(),
+        expected: expected.to_string(),
+        got,
+    }
+}
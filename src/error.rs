@@ -0,0 +1,118 @@
+use std::fmt;
+use std::rc::Rc;
+use crate::types::Value;
+use crate::source::Span;
+
+/// Errors that can occur while evaluating Lisp code
+#[derive(Debug, Clone)]
+pub enum LispError {
+    /// A symbol was looked up but has no binding
+    UnboundSymbol(String),
+    /// A `letrec` variable was used before its init had been evaluated
+    Unassigned(String),
+    /// The head of a form evaluated to something that cannot be called
+    NotCallable(String),
+    /// A procedure was called with the wrong number of arguments
+    ArityMismatch {
+        name: String,
+        expected: String,
+        got: usize,
+    },
+    /// An argument had the wrong type
+    TypeMismatch {
+        expected: String,
+        got: String,
+    },
+    /// Division by zero
+    DivisionByZero,
+    /// A Lisp value could not be converted to the requested Rust type
+    Conversion {
+        target: String,
+        value: String,
+        reason: String,
+    },
+    /// An argument of a typed Rust function could not be converted.
+    /// `index` counts from 1 and `error` is usually a `Conversion`.
+    Argument {
+        function: String,
+        index: usize,
+        error: Box<LispError>,
+    },
+    /// Any other error, described by a message
+    Custom(String),
+    /// The reader could not parse the input
+    Syntax(String),
+    /// The input ended in the middle of a form, so more input could complete it
+    Incomplete(String),
+    /// An error annotated with the source location of the form that caused it
+    Located {
+        error: Box<LispError>,
+        span: Span,
+    },
+}
+
+impl LispError {
+    /// Attaches `span` to this error unless it already carries a location
+    pub fn with_span(self, span: Span) -> LispError {
+        match self {
+            LispError::Located { .. } => self,
+            error => LispError::Located {
+                error: Box::new(error),
+                span,
+            },
+        }
+    }
+
+    /// The source location of this error, if known
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            LispError::Located { span, .. } => Some(span),
+            _ => None,
+        }
+    }
+
+    /// The error without any location information
+    pub fn kind(&self) -> &LispError {
+        match self {
+            LispError::Located { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+}
+
+/// The result of evaluating a Lisp expression
+pub type LispResult = Result<Rc<Value>, LispError>;
+
+impl fmt::Display for LispError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LispError::UnboundSymbol(name) => write!(f, "Unbound symbol: {}", name),
+            LispError::Unassigned(name) => write!(f, "Variable used before it was assigned: {}", name),
+            LispError::NotCallable(value) => write!(f, "Not callable: {}", value),
+            LispError::ArityMismatch { name, expected, got } => write!(
+                f,
+                "{}: expected {} argument(s), got {}",
+                name, expected, got
+            ),
+            LispError::TypeMismatch { expected, got } => {
+                write!(f, "Type mismatch: expected {}, got {}", expected, got)
+            }
+            LispError::DivisionByZero => write!(f, "Division by zero"),
+            LispError::Conversion { target, value, reason } => {
+                write!(f, "Cannot convert {} to {}: {}", value, target, reason)
+            }
+            LispError::Argument { function, index, error } => {
+                write!(f, "{}: argument {}: {}", function, index, error)
+            }
+            LispError::Custom(message) => write!(f, "{}", message),
+            LispError::Syntax(message) | LispError::Incomplete(message) => {
+                write!(f, "Syntax error: {}", message)
+            }
+            LispError::Located { error, span } => {
+                write!(f, "{}: {}\n{}", span, error, span.snippet())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LispError {}
@@ -1,60 +1,245 @@
+use std::fs;
 use std::rc::Rc;
-use std::collections::HashMap;
-use std::cell::RefCell;
-use crate::types::{Value, Environment, car, cdr, cons};
-
-pub fn eval(expr: Rc<Value>, env: Rc<Environment>) -> Rc<Value> {
-    match &*expr {
-        Value::Symbol(s) => {
-            env.borrow().get(s).cloned().unwrap_or_else(|| Rc::new(Value::Nil))
+use crate::types::{Value, Environment, Tail, car, cdr, cons};
+use crate::error::{LispError, LispResult};
+use crate::printer::print_value;
+use crate::parser::read_all_named;
+use crate::source::{span_of, record_span};
+
+/// Evaluates an expression.
+///
+/// Lambda bodies and special forms hand their tail expression back to this
+/// loop instead of evaluating it recursively, so calls in tail position run
+/// in constant Rust stack space.
+///
+/// Errors are annotated with the location of the innermost form that has
+/// one, so they point as close to the cause as the reader allows.
+pub fn eval(expr: Rc<Value>, env: Rc<Environment>) -> LispResult {
+    let mut expr = expr;
+    let mut env = env;
+    loop {
+        match step(&expr, &env).map_err(|err| locate(err, &expr))? {
+            Tail::Return(value) => return Ok(value),
+            Tail::Eval(next_expr, next_env) => {
+                expr = next_expr;
+                env = next_env;
+            }
         }
+    }
+}
+
+/// Evaluates one form, returning either its value or the next form to evaluate
+fn step(expr: &Rc<Value>, env: &Rc<Environment>) -> Result<Tail, LispError> {
+    match &**expr {
+        Value::Symbol(s) => match env.get(s) {
+            Some(value) if matches!(*value, Value::Unassigned) => Err(LispError::Unassigned(s.clone())),
+            Some(value) => Ok(Tail::Return(value)),
+            None => Err(LispError::UnboundSymbol(s.clone())),
+        },
         Value::Cons(_, _) => {
-            let func = eval(car(&expr), env.clone());
+            let func = eval(car(expr), env.clone())?;
             match &*func {
-                Value::Procedure(_, f) => f(cdr(&expr), env),
-                Value::Lambda(params, body, closure_env) => {
-                    let args = eval_list(cdr(&expr), env.clone());
-                    let new_env = Rc::new(RefCell::new(HashMap::new()));
-                    bind_params(params.clone(), args, new_env.clone(), closure_env.clone());
-                    eval(body.clone(), new_env)
+                Value::SpecialForm(_, f) => f(cdr(expr), env.clone()),
+                Value::Procedure(_, _) | Value::Lambda(_, _, _) => {
+                    let args = eval_list(cdr(expr), env.clone())?;
+                    apply_tail(&func, args, env)
+                }
+                Value::Macro(params, body, macro_env) => {
+                    // The expansion replaces the call and is evaluated in its place
+                    let expansion = expand_macro(params, body, macro_env, cdr(expr))?;
+                    if span_of(&expansion).is_none()
+                        && let Some(span) = span_of(expr)
+                    {
+                        record_span(&expansion, span);
+                    }
+                    Ok(Tail::Eval(expansion, env.clone()))
                 }
-                _ => Rc::new(Value::Nil),
+                _ => Err(LispError::NotCallable(print_value(&func))),
             }
         }
-        _ => expr,
+        _ => Ok(Tail::Return(expr.clone())),
+    }
+}
+
+/// Calls a procedure or lambda with a list of evaluated arguments. A lambda
+/// body's last expression is handed back as a tail call.
+fn apply_tail(func: &Rc<Value>, args: Rc<Value>, env: &Rc<Environment>) -> Result<Tail, LispError> {
+    match &**func {
+        Value::Procedure(_, f) => f(args, env.clone()).map(Tail::Return),
+        Value::Lambda(params, body, closure_env) => {
+            check_arity("lambda", params, &args)?;
+            let new_env = Rc::new(Environment::with_parent(closure_env.clone()));
+            bind_params(params.clone(), args, new_env.clone());
+            eval_body_tail(body.clone(), new_env)
+        }
+        _ => Err(LispError::NotCallable(print_value(func))),
+    }
+}
+
+/// Applies a procedure or lambda to arguments that are already evaluated.
+///
+/// Unlike evaluating a call expression built from them, the arguments are
+/// passed on as they are, so lists and symbols reach the function intact.
+/// Rust procedures are given an empty environment as the caller's; use
+/// `apply_in` to give them another.
+pub fn apply(func: &Rc<Value>, args: &[Rc<Value>]) -> LispResult {
+    apply_in(func, args, &Rc::new(Environment::new()))
+}
+
+/// Like `apply`, giving Rust procedures `env` as the caller's environment
+pub fn apply_in(func: &Rc<Value>, args: &[Rc<Value>], env: &Rc<Environment>) -> LispResult {
+    let args = args
+        .iter()
+        .rev()
+        .fold(Rc::new(Value::Nil), |list, arg| cons(arg.clone(), list));
+    match apply_tail(func, args, env)? {
+        Tail::Return(value) => Ok(value),
+        Tail::Eval(expr, env) => eval(expr, env),
+    }
+}
+
+/// Attaches the location `expr` was read from to `err`, if it has none yet
+fn locate(err: LispError, expr: &Rc<Value>) -> LispError {
+    match span_of(expr) {
+        Some(span) => err.with_span(span),
+        None => err,
+    }
+}
+
+/// Reads and evaluates every form in `input` in order.
+/// Returns the value of the last form, or the first error.
+pub fn eval_str(input: &str, env: Rc<Environment>) -> LispResult {
+    eval_str_named(input, "<input>", env)
+}
+
+/// Like `eval_str`, attributing source locations to `file`
+pub fn eval_str_named(input: &str, file: &str, env: Rc<Environment>) -> LispResult {
+    eval_program(read_all_named(input, file)?, env)
+}
+
+/// Reads and evaluates every form in the file at `path` in order.
+/// Returns the value of the last form, or the first error.
+pub fn eval_file(path: &str, env: Rc<Environment>) -> LispResult {
+    let source = fs::read_to_string(path)
+        .map_err(|err| LispError::Custom(format!("Cannot read {}: {}", path, err)))?;
+    eval_program(read_all_named(&source, path)?, env)
+}
+
+fn eval_program(exprs: Vec<Rc<Value>>, env: Rc<Environment>) -> LispResult {
+    let mut result = Rc::new(Value::Nil);
+    for expr in exprs {
+        result = eval(expr, env.clone())?;
     }
+    Ok(result)
 }
 
-pub fn eval_list(exprs: Rc<Value>, env: Rc<Environment>) -> Rc<Value> {
+pub fn eval_list(exprs: Rc<Value>, env: Rc<Environment>) -> LispResult {
     if let Value::Nil = *exprs {
-        return Rc::new(Value::Nil);
+        return Ok(Rc::new(Value::Nil));
     }
-    cons(
-        eval(car(&exprs), env.clone()),
-        eval_list(cdr(&exprs), env),
-    )
+    Ok(cons(
+        eval(car(&exprs), env.clone())?,
+        eval_list(cdr(&exprs), env)?,
+    ))
 }
 
-pub fn bind_params(params: Rc<Value>, args: Rc<Value>, env: Rc<Environment>, outer_env: Rc<Environment>) {
-    match (&*params, &*args) {
-        (Value::Nil, Value::Nil) => {
-            // Copy outer environment
-            for (k, v) in outer_env.borrow().iter() {
-                env.borrow_mut().insert(k.clone(), v.clone());
-            }
+/// Evaluates a sequence of expressions in order, returning the value of the last one
+pub fn eval_body(body: Rc<Value>, env: Rc<Environment>) -> LispResult {
+    match eval_body_tail(body, env)? {
+        Tail::Return(value) => Ok(value),
+        Tail::Eval(expr, env) => eval(expr, env),
+    }
+}
+
+/// Evaluates all but the last expression of a body and returns the last one
+/// as a tail call for the evaluator loop
+pub fn eval_body_tail(body: Rc<Value>, env: Rc<Environment>) -> Result<Tail, LispError> {
+    let mut current = body;
+    loop {
+        if !matches!(&*current, Value::Cons(_, _)) {
+            return Ok(Tail::Return(Rc::new(Value::Nil)));
         }
-        (Value::Symbol(name), _) => {
-            env.borrow_mut().insert(name.clone(), args);
-            // Copy outer environment
-            for (k, v) in outer_env.borrow().iter() {
-                if k != name {
-                    env.borrow_mut().insert(k.clone(), v.clone());
-                }
-            }
+        let rest = cdr(&current);
+        if matches!(&*rest, Value::Nil) {
+            return Ok(Tail::Eval(car(&current), env));
         }
-        (Value::Cons(p_car, p_cdr), Value::Cons(a_car, a_cdr)) => {
-            bind_params(p_car.clone(), a_car.clone(), env.clone(), outer_env.clone());
-            bind_params(p_cdr.clone(), a_cdr.clone(), env, outer_env);
+        eval(car(&current), env.clone())?;
+        current = rest;
+    }
+}
+
+/// Runs a macro's body with its parameters bound to the unevaluated argument forms
+pub fn expand_macro(params: &Rc<Value>, body: &Rc<Value>, macro_env: &Rc<Environment>, args: Rc<Value>) -> LispResult {
+    check_arity("macro", params, &args)?;
+    let expand_env = Rc::new(Environment::with_parent(macro_env.clone()));
+    bind_params(params.clone(), args, expand_env.clone());
+    eval_body(body.clone(), expand_env)
+}
+
+/// Expands `form` once if its head names a macro in `env`.
+/// Returns the form unchanged, and `false`, when it is not a macro call.
+pub fn macroexpand_1(form: Rc<Value>, env: &Rc<Environment>) -> Result<(Rc<Value>, bool), LispError> {
+    if let Value::Symbol(name) = &*car(&form)
+        && let Some(value) = env.get(name)
+        && let Value::Macro(params, body, macro_env) = &*value
+    {
+        return Ok((expand_macro(params, body, macro_env, cdr(&form))?, true));
+    }
+    Ok((form, false))
+}
+
+/// Expands `form` repeatedly until its head is no longer a macro
+pub fn macroexpand(form: Rc<Value>, env: &Rc<Environment>) -> LispResult {
+    let mut form = form;
+    loop {
+        let (expanded, changed) = macroexpand_1(form, env)?;
+        if !changed {
+            return Ok(expanded);
+        }
+        form = expanded;
+    }
+}
+
+/// Checks that a parameter list accepts the given argument list
+fn check_arity(name: &str, params: &Rc<Value>, args: &Rc<Value>) -> Result<(), LispError> {
+    let mut required = 0;
+    let mut current = params.clone();
+    while let Value::Cons(_, _) = &*current {
+        required += 1;
+        current = cdr(&current);
+    }
+    let variadic = matches!(&*current, Value::Symbol(_));
+
+    let mut got = 0;
+    let mut current = args.clone();
+    while let Value::Cons(_, _) = &*current {
+        got += 1;
+        current = cdr(&current);
+    }
+
+    if got == required || (variadic && got > required) {
+        return Ok(());
+    }
+    let expected = if variadic {
+        format!("at least {}", required)
+    } else {
+        required.to_string()
+    };
+    Err(LispError::ArityMismatch {
+        name: name.to_string(),
+        expected,
+        got,
+    })
+}
+
+/// Binds each parameter to the matching argument in `env`.
+/// A symbol in tail position collects the remaining arguments as a list.
+pub fn bind_params(params: Rc<Value>, args: Rc<Value>, env: Rc<Environment>) {
+    match (&*params, &*args) {
+        (Value::Symbol(name), _) => env.define(name, args),
+        (Value::Cons(_, _), Value::Cons(_, _)) => {
+            bind_params(car(&params), car(&args), env.clone());
+            bind_params(cdr(&params), cdr(&args), env);
         }
         _ => {}
     }
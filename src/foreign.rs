@@ -0,0 +1,279 @@
+//! Rust values carried inside Lisp values.
+//!
+//! A foreign value wraps any `'static` Rust value, such as a file handle, a
+//! parsed configuration or a database connection, so that scripts can hold
+//! it and hand it back to Rust functions without seeing inside it.
+//!
+//! How the values of a type print, compare and hash is described by a
+//! `ForeignType` registered for that type. Values of an unregistered type
+//! print with their Rust type name and are only equal to themselves.
+//!
+//! The methods `(send obj 'method args...)` can call are defined in an
+//! environment with `define_foreign_method`, so each interpreter has its own.
+//!
+//! The garbage collector does not look inside foreign values, so a Rust
+//! value that holds Lisp closures can keep them alive in a cycle.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::rc::Rc;
+use crate::types::{Value, Environment};
+use crate::error::LispError;
+use crate::convert::{FromLisp, IntoLisp, conversion_error, short_type_name, type_name};
+use crate::rust_functions::TypedFunction;
+
+type DisplayHook = Box<dyn Fn(&dyn Any) -> String>;
+type EqualityHook = Box<dyn Fn(&dyn Any, &dyn Any) -> bool>;
+type HashHook = Box<dyn Fn(&dyn Any) -> u64>;
+
+/// How the values of one Rust type behave in Lisp, with the type erased
+struct ForeignClass {
+    name: String,
+    display: Option<DisplayHook>,
+    equals: Option<EqualityHook>,
+    hash: Option<HashHook>,
+}
+
+thread_local! {
+    static FOREIGN_TYPES: RefCell<HashMap<TypeId, Rc<ForeignClass>>> = RefCell::new(HashMap::new());
+}
+
+fn class_of(type_id: TypeId) -> Option<Rc<ForeignClass>> {
+    FOREIGN_TYPES.with(|types| types.borrow().get(&type_id).cloned())
+}
+
+/// The name Lisp uses for `T`
+fn lisp_type_name<T: Any>() -> String {
+    class_of(TypeId::of::<T>()).map_or_else(type_name::<T>, |class| class.name.clone())
+}
+
+/// A Rust value held by Lisp code
+#[derive(Clone)]
+pub struct Foreign {
+    object: Rc<dyn Any>,
+    rust_type: &'static str,
+}
+
+impl Foreign {
+    /// Wraps a Rust value
+    pub fn new<T: Any>(value: T) -> Self {
+        Foreign::from_rc(Rc::new(value))
+    }
+
+    /// Wraps a Rust value that is already shared
+    pub fn from_rc<T: Any>(value: Rc<T>) -> Self {
+        Foreign {
+            object: value,
+            rust_type: std::any::type_name::<T>(),
+        }
+    }
+
+    fn class(&self) -> Option<Rc<ForeignClass>> {
+        class_of((*self.object).type_id())
+    }
+
+    /// The name given when the type was registered, or else its Rust name
+    pub fn type_name(&self) -> String {
+        match self.class() {
+            Some(class) => class.name.clone(),
+            None => short_type_name(self.rust_type),
+        }
+    }
+
+    /// Whether the wrapped value is a `T`
+    pub fn is<T: Any>(&self) -> bool {
+        self.object.is::<T>()
+    }
+
+    /// The wrapped value, if it is a `T`
+    pub fn downcast<T: Any>(&self) -> Option<Rc<T>> {
+        self.object.clone().downcast().ok()
+    }
+
+    /// The `TypeId` of the wrapped value, which methods are defined for
+    pub(crate) fn rust_type_id(&self) -> TypeId {
+        (*self.object).type_id()
+    }
+}
+
+impl PartialEq for Foreign {
+    // Without an equality hook, a value is only equal to itself
+    fn eq(&self, other: &Self) -> bool {
+        if std::ptr::addr_eq(Rc::as_ptr(&self.object), Rc::as_ptr(&other.object)) {
+            return true;
+        }
+        if (*self.object).type_id() != (*other.object).type_id() {
+            return false;
+        }
+        self.class()
+            .and_then(|class| class.equals.as_ref().map(|equals| equals(&*self.object, &*other.object)))
+            .unwrap_or(false)
+    }
+}
+
+impl Eq for Foreign {}
+
+impl Hash for Foreign {
+    // Must agree with `eq`: values equal through the equality hook need equal
+    // hashes, so without a hash hook they all share the hash of their type
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let class = self.class();
+        match class.as_ref().map(|class| (&class.hash, &class.equals)) {
+            Some((Some(hash), _)) => hash(&*self.object).hash(state),
+            Some((None, Some(_))) => (*self.object).type_id().hash(state),
+            _ => (Rc::as_ptr(&self.object) as *const () as usize).hash(state),
+        }
+    }
+}
+
+impl fmt::Display for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = self.class();
+        match class.as_ref().and_then(|class| class.display.as_ref()) {
+            Some(display) => write!(f, "{}", display(&*self.object)),
+            None => write!(f, "#<{}>", self.type_name()),
+        }
+    }
+}
+
+impl fmt::Debug for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Foreign").field(&self.rust_type).finish()
+    }
+}
+
+impl IntoLisp for Foreign {
+    fn into_lisp(self) -> Rc<Value> {
+        Rc::new(Value::Foreign(self))
+    }
+}
+
+/// A typed handle to the Rust value inside a foreign value. As a parameter
+/// of a typed function it accepts only foreign values holding a `T`.
+pub struct ForeignRef<T>(Rc<T>);
+
+impl<T> ForeignRef<T> {
+    /// Wraps a Rust value, to be returned to Lisp
+    pub fn new(value: T) -> Self {
+        ForeignRef(Rc::new(value))
+    }
+
+    /// The shared Rust value
+    pub fn into_rc(self) -> Rc<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for ForeignRef<T> {
+    fn clone(&self) -> Self {
+        ForeignRef(self.0.clone())
+    }
+}
+
+impl<T> Deref for ForeignRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Any> FromLisp for ForeignRef<T> {
+    fn from_lisp(value: &Rc<Value>) -> Result<Self, LispError> {
+        match &**value {
+            Value::Foreign(foreign) => foreign.downcast().map(ForeignRef).ok_or_else(|| {
+                let reason = format!("expected a {}, got a {}", lisp_type_name::<T>(), foreign.type_name());
+                conversion_error::<T>(value, reason)
+            }),
+            _ => Err(conversion_error::<T>(value, "expected a foreign value")),
+        }
+    }
+}
+
+impl<T: Any> IntoLisp for ForeignRef<T> {
+    fn into_lisp(self) -> Rc<Value> {
+        Foreign::from_rc(self.0).into_lisp()
+    }
+}
+
+/// Describes how the values of `T` print, compare and hash. Every part is optional.
+///
+/// ```ignore
+/// ForeignType::<Config>::new("config")
+///     .display(|config| format!("#<config {}>", config.path))
+///     .equality(PartialEq::eq)
+///     .register();
+/// ```
+pub struct ForeignType<T> {
+    class: ForeignClass,
+    marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Any> ForeignType<T> {
+    /// Starts describing `T`, which Lisp will call `name`
+    pub fn new(name: &str) -> Self {
+        ForeignType {
+            class: ForeignClass {
+                name: name.to_string(),
+                display: None,
+                equals: None,
+                hash: None,
+            },
+            marker: PhantomData,
+        }
+    }
+
+    /// How values are printed, instead of `#<name>`
+    pub fn display(mut self, display: impl Fn(&T) -> String + 'static) -> Self {
+        self.class.display = Some(Box::new(move |object| display(downcast_ref(object))));
+        self
+    }
+
+    /// When two distinct values are `equal?`, instead of never
+    pub fn equality(mut self, equals: impl Fn(&T, &T) -> bool + 'static) -> Self {
+        self.class.equals = Some(Box::new(move |a, b| equals(downcast_ref(a), downcast_ref(b))));
+        self
+    }
+
+    /// The hash used for `Foreign` keys in Rust collections, which must
+    /// agree with `equality`. Without it, values are hashed by address, or
+    /// all alike if an equality hook is set, which is correct but slow.
+    pub fn hash(mut self, hash: impl Fn(&T) -> u64 + 'static) -> Self {
+        self.class.hash = Some(Box::new(move |object| hash(downcast_ref(object))));
+        self
+    }
+
+    /// Makes this description apply to every value of `T` on this thread,
+    /// since values can pass between interpreters. Replaces any earlier one.
+    pub fn register(self) {
+        FOREIGN_TYPES.with(|types| {
+            types.borrow_mut().insert(TypeId::of::<T>(), Rc::new(self.class));
+        });
+    }
+}
+
+/// Defines a method in `env` that `(send obj 'name args...)` calls for
+/// values of `T`, with the object as its first argument, typically taken as
+/// a `ForeignRef<T>`. Register `T` first so errors use its Lisp name.
+///
+/// ```ignore
+/// define_foreign_method::<Config, _>(interp.env(), "get", |config: ForeignRef<Config>, key: String| {
+///     config.get(&key)
+/// });
+/// ```
+pub fn define_foreign_method<T: Any, Args>(env: &Environment, name: &str, method: impl TypedFunction<Args>) {
+    let full_name = format!("{}.{}", lisp_type_name::<T>(), name);
+    env.define_method(TypeId::of::<T>(), name, method.into_native(&full_name));
+}
+
+/// The hooks of a class are only called with values of its own type
+fn downcast_ref<T: Any>(object: &dyn Any) -> &T {
+    object
+        .downcast_ref()
+        .expect("foreign type hook called with a value of another type")
+}
@@ -0,0 +1,233 @@
+//! Cycle collection for closures and the environments they capture.
+//!
+//! A lambda holds the environment it was created in, and `define` stores the
+//! lambda back into an environment, so reference counting alone never frees
+//! a recursive function. Every environment captured by a closure is tracked
+//! here, and the collector periodically traces the graph of environments,
+//! pairs and closures reachable from them.
+//!
+//! Roots are found by trial deletion: any node with more strong references
+//! than the graph itself accounts for is held from outside — by the host
+//! application or by an evaluation in progress — and everything reachable
+//! from it stays alive. The remaining environments and pairs are garbage;
+//! clearing them breaks their cycles and lets `Rc` free them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::types::{Value, Environment};
+
+/// Collections run after this many closures are created, or more once the heap grows
+const MIN_THRESHOLD: usize = 10_000;
+
+#[derive(Default)]
+struct Heap {
+    /// Environments captured by closures since they were created
+    tracked: Vec<Weak<Environment>>,
+    /// Closures created since the last collection
+    allocations: usize,
+    /// Size of the graph that survived the last collection
+    live_nodes: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::default());
+}
+
+/// Registers an environment that a closure has captured, collecting
+/// garbage first if enough closures have been created since the last run
+pub(crate) fn track(env: &Rc<Environment>) {
+    let due = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.tracked.push(Rc::downgrade(env));
+        heap.allocations += 1;
+        heap.allocations >= heap.live_nodes.max(MIN_THRESHOLD)
+    });
+    if due {
+        collect_garbage();
+    }
+}
+
+/// The number of tracked environments that are still alive
+pub fn tracked_environments() -> usize {
+    HEAP.with(|heap| {
+        heap.borrow()
+            .tracked
+            .iter()
+            .filter(|env| env.strong_count() > 0)
+            .count()
+    })
+}
+
+/// A node of the traced graph
+enum Node {
+    Env(Rc<Environment>),
+    Value(Rc<Value>),
+}
+
+impl Node {
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Env(env) => Rc::strong_count(env),
+            Node::Value(value) => Rc::strong_count(value),
+        }
+    }
+}
+
+/// The environments, pairs and closures reachable from the tracked
+/// environments, with the number of references each receives from the others
+#[derive(Default)]
+struct Graph {
+    index: HashMap<*const (), usize>,
+    nodes: Vec<Node>,
+    internal: Vec<usize>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn add(&mut self, key: *const (), node: impl FnOnce() -> Node) -> usize {
+        if let Some(&i) = self.index.get(&key) {
+            return i;
+        }
+        self.index.insert(key, self.nodes.len());
+        self.nodes.push(node());
+        self.internal.push(0);
+        self.edges.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    fn add_env(&mut self, env: &Rc<Environment>) -> usize {
+        self.add(Rc::as_ptr(env) as *const (), || Node::Env(env.clone()))
+    }
+
+    /// Adds a value if it can refer to other nodes; atoms cannot be part of a cycle
+    fn add_value(&mut self, value: &Rc<Value>) -> Option<usize> {
+        match &**value {
+            Value::Cons(_, _) | Value::Lambda(_, _, _) | Value::Macro(_, _, _) => {
+                Some(self.add(Rc::as_ptr(value) as *const (), || Node::Value(value.clone())))
+            }
+            _ => None,
+        }
+    }
+
+    fn link(&mut self, from: usize, to: usize) {
+        self.internal[to] += 1;
+        self.edges[from].push(to);
+    }
+
+    fn link_value(&mut self, from: usize, value: &Rc<Value>) {
+        if let Some(to) = self.add_value(value) {
+            self.link(from, to);
+        }
+    }
+
+    /// Follows the references out of node `i`. Returns `None` if a cell is
+    /// currently borrowed for writing, in which case it is not safe to collect.
+    fn trace(&mut self, i: usize) -> Option<()> {
+        match &self.nodes[i] {
+            Node::Env(env) => {
+                let env = env.clone();
+                if let Some(parent) = env.parent() {
+                    let to = self.add_env(parent);
+                    self.link(i, to);
+                }
+                let mut values = Vec::new();
+                env.try_for_each_value(|value| values.push(value.clone()))?;
+                for value in &values {
+                    self.link_value(i, value);
+                }
+            }
+            Node::Value(value) => {
+                let value = value.clone();
+                match &*value {
+                    Value::Cons(car, cdr) => {
+                        let car = car.try_borrow().ok()?.clone();
+                        let cdr = cdr.try_borrow().ok()?.clone();
+                        self.link_value(i, &car);
+                        self.link_value(i, &cdr);
+                    }
+                    Value::Lambda(params, body, env) | Value::Macro(params, body, env) => {
+                        self.link_value(i, params);
+                        self.link_value(i, body);
+                        let to = self.add_env(env);
+                        self.link(i, to);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Reclaims environments and pairs that are only reachable from each other.
+/// Returns the number of environments cleared.
+pub fn collect_garbage() -> usize {
+    let tracked = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().tracked));
+
+    let mut graph = Graph::default();
+    let mut tracked_nodes = Vec::new();
+    for env in tracked.iter().filter_map(Weak::upgrade) {
+        tracked_nodes.push(graph.add_env(&env));
+    }
+    tracked_nodes.sort_unstable();
+    tracked_nodes.dedup();
+
+    let mut i = 0;
+    while i < graph.nodes.len() {
+        if graph.trace(i).is_none() {
+            // Something is mid-update; try again at the next collection
+            HEAP.with(|heap| heap.borrow_mut().tracked.extend(tracked));
+            return 0;
+        }
+        i += 1;
+    }
+
+    // Nodes referenced from outside the graph are roots; the graph itself
+    // holds one extra reference to every node
+    let mut marked = vec![false; graph.nodes.len()];
+    let mut stack: Vec<usize> = (0..graph.nodes.len())
+        .filter(|&i| graph.nodes[i].strong_count() > graph.internal[i] + 1)
+        .collect();
+    while let Some(i) = stack.pop() {
+        if !marked[i] {
+            marked[i] = true;
+            stack.extend(graph.edges[i].iter().copied().filter(|&to| !marked[to]));
+        }
+    }
+
+    let mut cleared = 0;
+    for (node, _) in graph.nodes.iter().zip(&marked).filter(|(_, marked)| !**marked) {
+        match node {
+            Node::Env(env) => {
+                env.clear();
+                cleared += 1;
+            }
+            Node::Value(value) => {
+                if let Value::Cons(car, cdr) = &**value {
+                    car.replace(Rc::new(Value::Nil));
+                    cdr.replace(Rc::new(Value::Nil));
+                }
+            }
+        }
+    }
+
+    let survivors: Vec<Weak<Environment>> = tracked_nodes
+        .into_iter()
+        .filter(|&i| marked[i])
+        .filter_map(|i| match &graph.nodes[i] {
+            Node::Env(env) => Some(Rc::downgrade(env)),
+            Node::Value(_) => None,
+        })
+        .collect();
+    let live_nodes = marked.iter().filter(|marked| **marked).count();
+    drop(graph);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.tracked.extend(survivors);
+        heap.allocations = 0;
+        heap.live_nodes = live_nodes;
+    });
+    cleared
+}
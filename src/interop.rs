@@ -1,44 +1,40 @@
+use std::any::Any;
 use std::rc::Rc;
-use crate::types::{Value, Environment, cons};
-use crate::eval::eval;
+use crate::types::{Value, Environment, car, cdr, cons};
+use crate::number::Number;
+use crate::eval::apply_in;
+use crate::error::{LispError, LispResult};
+use crate::foreign::Foreign;
 
 /// Calls a Lisp function from Rust code
 ///
 /// # Arguments
 ///
 /// * `func_name` - The name of the Lisp function to call
-/// * `args` - A vector of Lisp values to pass as arguments
+/// * `args` - A vector of Lisp values to pass as arguments, which are not evaluated again
 /// * `env` - The Lisp environment
 ///
 /// # Returns
 ///
-/// The result of the function call as a Lisp value
-pub fn call_lisp_function(func_name: &str, args: Vec<Rc<Value>>, env: Rc<Environment>) -> Rc<Value> {
+/// The result of the function call as a Lisp value, or the error raised while evaluating it
+pub fn call_lisp_function(func_name: &str, args: Vec<Rc<Value>>, env: Rc<Environment>) -> LispResult {
     // Look up the function in the environment
-    let func = match env.borrow().get(func_name) {
-        Some(f) => f.clone(),
-        None => {
-            println!("Function '{}' not found in environment", func_name);
-            return Rc::new(Value::Nil);
-        }
-    };
-
-    // Build a Lisp list from the arguments
-    let mut arg_list = Rc::new(Value::Nil);
-    for arg in args.iter().rev() {
-        arg_list = cons(arg.clone(), arg_list);
-    }
-
-    // Build the function call expression: (func arg1 arg2 ...)
-    let expr = cons(func.clone(), arg_list);
+    let func = env
+        .get(func_name)
+        .ok_or_else(|| LispError::UnboundSymbol(func_name.to_string()))?;
 
-    // Evaluate the expression
-    eval(expr, env)
+    apply_in(&func, &args, &env)
 }
 
-/// Converts a Rust f64 to a Lisp number
+/// Converts a Rust f64 to an inexact Lisp number.
+/// See `FromLisp` and `IntoLisp` for checked conversions of many more types.
 pub fn rust_to_lisp_number(n: f64) -> Rc<Value> {
-    Rc::new(Value::Number(n))
+    Rc::new(Value::Number(Number::Real(n)))
+}
+
+/// Converts a Rust i64 to an exact Lisp integer
+pub fn rust_to_lisp_integer(n: i64) -> Rc<Value> {
+    Rc::new(Value::Number(Number::Integer(n)))
 }
 
 /// Converts a Rust string to a Lisp symbol
@@ -46,6 +42,11 @@ pub fn rust_to_lisp_symbol(s: &str) -> Rc<Value> {
     Rc::new(Value::Symbol(s.to_string()))
 }
 
+/// Converts a Rust string to a Lisp string
+pub fn rust_to_lisp_string(s: &str) -> Rc<Value> {
+    Rc::new(Value::String(s.to_string()))
+}
+
 /// Converts a Rust boolean to a Lisp boolean
 pub fn rust_to_lisp_bool(b: bool) -> Rc<Value> {
     Rc::new(Value::Bool(b))
@@ -63,7 +64,7 @@ pub fn rust_to_lisp_list(v: Vec<Rc<Value>>) -> Rc<Value> {
 /// Attempts to convert a Lisp value to a Rust f64
 pub fn lisp_to_rust_number(v: &Rc<Value>) -> Option<f64> {
     match &**v {
-        Value::Number(n) => Some(*n),
+        Value::Number(n) => Some(n.to_f64()),
         _ => None,
     }
 }
@@ -71,7 +72,7 @@ pub fn lisp_to_rust_number(v: &Rc<Value>) -> Option<f64> {
 /// Attempts to convert a Lisp value to a Rust String
 pub fn lisp_to_rust_string(v: &Rc<Value>) -> Option<String> {
     match &**v {
-        Value::Symbol(s) => Some(s.clone()),
+        Value::String(s) => Some(s.clone()),
         _ => None,
     }
 }
@@ -90,31 +91,45 @@ pub fn lisp_to_rust_vector(v: &Rc<Value>) -> Vec<Rc<Value>> {
     let mut result = Vec::new();
     let mut current = v.clone();
 
-    while let Value::Cons(car_val, cdr_val) = &*current {
-        result.push(car_val.clone());
-        current = cdr_val.clone();
+    while let Value::Cons(_, _) = &*current {
+        result.push(car(&current));
+        current = cdr(&current);
     }
 
     result
 }
 
+/// Wraps any Rust value, such as a file handle or a connection, as an opaque Lisp value
+pub fn rust_to_lisp_foreign<T: Any>(value: T) -> Rc<Value> {
+    Rc::new(Value::Foreign(Foreign::new(value)))
+}
+
+/// Attempts to get the Rust value out of a value made by `rust_to_lisp_foreign`.
+/// Returns `None` if it is not a foreign value or holds a different type.
+pub fn lisp_to_rust_foreign<T: Any>(v: &Rc<Value>) -> Option<Rc<T>> {
+    match &**v {
+        Value::Foreign(foreign) => foreign.downcast(),
+        _ => None,
+    }
+}
+
 /// Registers a Lisp function in the environment
 ///
 /// This is a helper function to define Lisp functions from Rust
-pub fn register_lisp_function(name: &str, params: &str, body: &str, env: Rc<Environment>) -> Result<(), String> {
+pub fn register_lisp_function(name: &str, params: &str, body: &str, env: Rc<Environment>) -> Result<(), LispError> {
     // Parse the parameters and body
     let params_expr = crate::parser::read(params)?;
     let body_expr = crate::parser::read(body)?;
 
-    // Create a lambda expression
-    let lambda = Rc::new(Value::Lambda(
+    // Create a lambda expression whose body is the single parsed form
+    let lambda = Value::new_lambda(
         params_expr,
-        body_expr,
+        cons(body_expr, Rc::new(Value::Nil)),
         env.clone(),
-    ));
+    );
 
     // Register the function in the environment
-    env.borrow_mut().insert(name.to_string(), lambda);
+    env.define(name, lambda);
 
     Ok(())
 }
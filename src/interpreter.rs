@@ -0,0 +1,143 @@
+use std::any::Any;
+use std::rc::Rc;
+use crate::types::{Value, Environment};
+use crate::error::{LispError, LispResult};
+use crate::eval::{eval, eval_file, eval_str_named, apply_in};
+use crate::callable::{IntoLispArgs, LispCallable};
+use crate::convert::FromLisp;
+use crate::foreign::define_foreign_method;
+use crate::environment::{setup_environment, set_command_line};
+use crate::interop::call_lisp_function;
+use crate::rust_functions::{RustFunctionRegistry, TypedFunction, install_rust_call, rust_procedure, typed_procedure};
+
+/// Settings for an `Interpreter`
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    /// The file name reported in error locations for code passed to `eval_str`
+    pub source_name: String,
+    /// The list returned by `(command-line)`: a program name followed by its arguments
+    pub command_line: Vec<String>,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        InterpreterConfig {
+            source_name: "<input>".to_string(),
+            command_line: Vec::new(),
+        }
+    }
+}
+
+/// A self-contained Lisp interpreter.
+///
+/// Each interpreter owns its global environment and its registry of Rust
+/// functions, so several can run side by side on one thread without seeing
+/// each other's definitions.
+pub struct Interpreter {
+    env: Rc<Environment>,
+    functions: Rc<RustFunctionRegistry>,
+    config: InterpreterConfig,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Create an interpreter with the standard environment and default settings
+    pub fn new() -> Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+
+    /// Create an interpreter with the standard environment and the given settings
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        let env = setup_environment();
+        let functions = Rc::new(RustFunctionRegistry::new());
+        install_rust_call(&env, functions.clone());
+
+        set_command_line(&env, config.command_line.clone());
+
+        Interpreter { env, functions, config }
+    }
+
+    /// The global environment
+    pub fn env(&self) -> &Rc<Environment> {
+        &self.env
+    }
+
+    /// The settings this interpreter was created with
+    pub fn config(&self) -> &InterpreterConfig {
+        &self.config
+    }
+
+    /// Evaluate an expression in the global environment
+    pub fn eval(&self, expr: Rc<Value>) -> LispResult {
+        eval(expr, self.env.clone())
+    }
+
+    /// Read and evaluate every form in `input`, returning the value of the last one
+    pub fn eval_str(&self, input: &str) -> LispResult {
+        eval_str_named(input, &self.config.source_name, self.env.clone())
+    }
+
+    /// Read and evaluate every form in a file, returning the value of the last one
+    pub fn eval_file(&self, path: &str) -> LispResult {
+        eval_file(path, self.env.clone())
+    }
+
+    /// Register a Rust function or closure, both as a procedure scripts can
+    /// call directly and as a target for `rust-call`. Returning `Err` raises
+    /// the error in the calling script.
+    pub fn register_fn(&self, name: &str, func: impl Fn(Vec<Rc<Value>>) -> LispResult + 'static) {
+        let func = Rc::new(func);
+        self.functions.register(name, func.clone());
+        self.env.define(name, rust_procedure(name, move |args| func(args)));
+    }
+
+    /// Register a Rust function with typed parameters, such as
+    /// `fn(f64, f64) -> f64` or `Fn(String) -> Result<String, E>`. Calls are
+    /// checked for the number and types of their arguments.
+    pub fn register_typed_fn<Args>(&self, name: &str, func: impl TypedFunction<Args>) {
+        self.env.define(name, typed_procedure(name, func));
+    }
+
+    /// Define a method that `(send obj 'name args...)` calls for foreign
+    /// values of `T` in this interpreter, with the object as its first argument
+    pub fn register_method<T: Any, Args>(&self, name: &str, method: impl TypedFunction<Args>) {
+        define_foreign_method::<T, Args>(&self.env, name, method);
+    }
+
+    /// Call the global function `name` with the given arguments
+    pub fn call(&self, name: &str, args: Vec<Rc<Value>>) -> LispResult {
+        call_lisp_function(name, args, self.env.clone())
+    }
+
+    /// Apply a procedure or lambda value to arguments, which are not evaluated again
+    pub fn apply(&self, func: &Rc<Value>, args: &[Rc<Value>]) -> LispResult {
+        apply_in(func, args, &self.env)
+    }
+
+    /// A typed handle to the global function `name`, to keep and call later.
+    /// Rust procedures called through it see the global environment, as with `apply`.
+    pub fn callable<Args: IntoLispArgs, Ret: FromLisp>(&self, name: &str) -> Result<LispCallable<Args, Ret>, LispError> {
+        let func = self.env.get(name).ok_or_else(|| LispError::UnboundSymbol(name.to_string()))?;
+        Ok(LispCallable::new(func)?.with_env(self.env.clone()))
+    }
+
+    /// The value bound to `name` in the global environment
+    pub fn get_global(&self, name: &str) -> Option<Rc<Value>> {
+        self.env.get(name)
+    }
+
+    /// Bind `name` to `value` in the global environment
+    pub fn set_global(&self, name: &str, value: Rc<Value>) {
+        self.env.define(name, value);
+    }
+
+    /// The names of the Rust functions registered with `register_fn`, sorted
+    pub fn function_names(&self) -> Vec<String> {
+        self.functions.names()
+    }
+}
@@ -1,31 +1,62 @@
 pub mod types;
+pub mod number;
+pub mod error;
 pub mod eval;
 pub mod parser;
+pub mod source;
 pub mod printer;
 pub mod environment;
+pub mod gc;
 pub mod interop;
+pub mod convert;
+pub mod foreign;
+pub mod callable;
 pub mod rust_functions;
+pub mod interpreter;
+pub mod repl;
 
 // Re-export commonly used items
 pub use types::{Value, Environment, car, cdr, cons};
-pub use eval::eval;
-pub use parser::read;
+pub use number::Number;
+pub use error::{LispError, LispResult};
+pub use eval::{eval, eval_str, eval_str_named, eval_file, apply, apply_in};
+pub use convert::{FromLisp, IntoLisp};
+pub use foreign::{Foreign, ForeignRef, ForeignType, define_foreign_method};
+pub use callable::{IntoLispArgs, LispCallable};
+pub use interpreter::{Interpreter, InterpreterConfig};
+pub use parser::{read, read_named, read_all, read_all_named};
+pub use source::Span;
 pub use printer::print_value;
-pub use environment::setup_environment;
+pub use gc::collect_garbage;
+pub use environment::{setup_environment, set_command_line, documentation};
 pub use interop::{
     call_lisp_function,
     rust_to_lisp_number,
+    rust_to_lisp_integer,
     rust_to_lisp_symbol,
+    rust_to_lisp_string,
     rust_to_lisp_bool,
     rust_to_lisp_list,
     lisp_to_rust_number,
     lisp_to_rust_string,
     lisp_to_rust_bool,
     lisp_to_rust_vector,
+    rust_to_lisp_foreign,
+    lisp_to_rust_foreign,
     register_lisp_function,
 };
+pub use rustlisp2_macros::{lisp_fn, lisp_module};
 pub use rust_functions::{
     register_rust_function,
+    define_rust_function,
+    rust_procedure,
+    define_typed_function,
+    typed_procedure,
+    TypedFunction,
+    IntoLispResult,
+    Rest,
+    rust_function_names,
+    default_registry,
     setup_rust_functions,
     rust_add,
     rust_multiply,
@@ -1,55 +1,226 @@
-use std::io::{self, Write, BufRead};
+use std::env;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+use std::process;
 use std::rc::Rc;
 
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
 // Import from our library
+use rustlisp2::repl::meta_command;
 use rustlisp2::{
     Environment,
-    eval,
-    read,
+    Interpreter,
+    InterpreterConfig,
+    LispError,
+    read_all_named,
     print_value,
-    setup_environment,
-    setup_rust_functions,
 };
 
+const USAGE: &str = "\
+Usage: rustlisp2 [script.lisp [args...]]
+       rustlisp2 -e '<expr>' [args...]
+       rustlisp2 - [args...]
+
+With no script, runs a program piped to stdin, or starts the REPL.";
+
 fn main() {
-    // Setup environment
-    let env = setup_environment();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("-h") | Some("--help") => println!("{}", USAGE),
+        Some("-e") => {
+            let Some(expr) = args.get(1) else {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            };
+            let interp = interpreter("<command-line>", script_args("-e", &args[2..]));
+            // One-liners print their result, since that is usually the point
+            let result = exit_on_error(interp.eval_str(expr));
+            println!("{}", print_value(&result));
+        }
+        Some("-") => run_stdin(&interpreter("<stdin>", script_args("-", &args[1..]))),
+        Some(path) => {
+            let interp = interpreter(path, script_args(path, &args[1..]));
+            exit_on_error(interp.eval_file(path));
+        }
+        None if !io::stdin().is_terminal() => {
+            run_stdin(&interpreter("<stdin>", script_args("-", &[])));
+        }
+        None => {
+            // Print welcome message
+            println!("RustLisp 🦀λ - A tiny Lisp interpreter");
+            println!("Type 'exit' to quit");
+            println!();
+
+            // Start the REPL
+            repl(interpreter("<repl>", Vec::new()));
+        }
+    }
+}
+
+/// An interpreter whose errors refer to `source_name`
+fn interpreter(source_name: &str, command_line: Vec<String>) -> Interpreter {
+    Interpreter::with_config(InterpreterConfig {
+        source_name: source_name.to_string(),
+        command_line,
+    })
+}
 
-    // Setup Rust functions
-    setup_rust_functions(env.clone());
+/// The list `(command-line)` returns: the program name followed by its arguments
+fn script_args(program: &str, args: &[String]) -> Vec<String> {
+    std::iter::once(program.to_string())
+        .chain(args.iter().cloned())
+        .collect()
+}
 
-    // Print welcome message
-    println!("RustLisp 🦀λ - A tiny Lisp interpreter");
-    println!("Type 'exit' to quit");
-    println!("");
+/// Reads a whole program from stdin and runs it
+fn run_stdin(interp: &Interpreter) {
+    let mut source = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut source) {
+        eprintln!("Error: cannot read stdin: {}", err);
+        process::exit(1);
+    }
+    exit_on_error(interp.eval_str(&source));
+}
 
-    // Start the REPL
-    repl(env);
+/// Unwraps a result, or reports the error and exits with a failure status
+fn exit_on_error<T>(result: Result<T, LispError>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    })
 }
 
-fn repl(env: Rc<Environment>) {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+/// Characters that end a symbol, used to find the word being completed
+const DELIMITERS: &[char] = &['(', ')', '\'', '`', ',', '"', ';'];
 
-    println!("RustLisp 🦀λ - A tiny Lisp interpreter");
+/// Line editor support for the REPL: tab completion of bound symbols
+struct LispHelper {
+    env: Rc<Environment>,
+}
 
-    loop {
-        print!("🦀λ> ");
-        stdout.flush().unwrap();
+impl Completer for LispHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(|c: char| c.is_whitespace() || DELIMITERS.contains(&c))
+            .map_or(0, |i| i + 1);
+        let prefix = &line[start..pos];
+
+        // Functions registered with `Interpreter::register_fn` are bound in
+        // the environment too, so its names already include them
+        let candidates = self
+            .env
+            .names()
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| Pair { display: name.clone(), replacement: name })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for LispHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LispHelper {}
+
+impl Validator for LispHelper {}
+
+impl Helper for LispHelper {}
+
+/// Where REPL history is kept between sessions
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rustlisp2_history"))
+}
 
-        let mut input = String::new();
-        if stdin.lock().read_line(&mut input).is_err() || input.trim() == "exit" {
+fn repl(interp: Interpreter) {
+    let mut interp = interp;
+    let mut editor: Editor<LispHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("Error: cannot start line editor: {}", err);
+            process::exit(1);
+        }
+    };
+    editor.set_helper(Some(LispHelper { env: interp.env().clone() }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session
+        let _ = editor.load_history(path);
+    }
+
+    // Lines are accumulated here until they hold only complete forms
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { "🦀λ> " } else { "  .. " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                // Ctrl-C abandons the form being typed
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                println!("Error: {}", err);
+                break;
+            }
+        };
+        if input.is_empty() && line.trim() == "exit" {
             break;
         }
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            if let Err(err) = meta_command(line.trim(), &mut interp, &mut io::stdout()) {
+                println!("Error: {}", err);
+            }
+            if let Some(helper) = editor.helper_mut() {
+                // `:reset` replaces the environment that completion draws on
+                helper.env = interp.env().clone();
+            }
+            let _ = editor.add_history_entry(line.trim());
+            continue;
+        }
+        input.push_str(&line);
+        input.push('\n');
 
-        match read(&input) {
-            Ok(expr) => {
-                let result = eval(expr, env.clone());
-                println!("{}", print_value(&result));
+        // Evaluate every form entered so far, stopping at the first error
+        match read_all_named(&input, &interp.config().source_name) {
+            Ok(exprs) => {
+                for expr in exprs {
+                    match interp.eval(expr) {
+                        Ok(result) => println!("{}", print_value(&result)),
+                        Err(err) => {
+                            println!("Error: {}", err);
+                            break;
+                        }
+                    }
+                }
             }
+            Err(err) if matches!(err.kind(), LispError::Incomplete(_)) => continue,
             Err(err) => {
                 println!("Error: {}", err);
             }
         }
+        // A multi-line form is recalled from history as a single entry
+        if !input.trim().is_empty() {
+            let _ = editor.add_history_entry(input.trim_end());
+        }
+        input.clear();
+    }
+
+    if let Some(path) = &history
+        && let Err(err) = editor.save_history(path)
+    {
+        eprintln!("Warning: cannot save history to {}: {}", path.display(), err);
     }
 }
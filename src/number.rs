@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use crate::error::LispError;
+
+/// A Lisp number: an exact integer, an exact rational or an inexact float.
+///
+/// Exact results are always kept in their simplest form, so a `Big` never
+/// fits in an `i64` and a `Rational` never has a denominator of 1.
+#[derive(Debug, Clone)]
+pub enum Number {
+    Integer(i64),
+    Big(BigInt),
+    Rational(BigRational),
+    Real(f64),
+}
+
+/// Both operands of a binary operation, converted to their common representation
+enum Operands {
+    Integers(i64, i64),
+    Bigs(BigInt, BigInt),
+    Rationals(BigRational, BigRational),
+    Reals(f64, f64),
+}
+
+impl Number {
+    /// Parses integer (`42`, `-7`), rational (`1/3`) and float (`2.5`, `1e10`,
+    /// `+inf.0`) literals. Returns `None` if `s` is not a number.
+    pub fn parse(s: &str) -> Option<Number> {
+        let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+        if unsigned.is_empty() {
+            return None;
+        }
+        match s {
+            "+inf.0" => return Some(Number::Real(f64::INFINITY)),
+            "-inf.0" => return Some(Number::Real(f64::NEG_INFINITY)),
+            "+nan.0" | "-nan.0" => return Some(Number::Real(f64::NAN)),
+            _ => {}
+        }
+
+        let all_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if all_digits(unsigned) {
+            return Some(match s.parse::<i64>() {
+                Ok(n) => Number::Integer(n),
+                Err(_) => Number::Big(s.parse().ok()?),
+            });
+        }
+        if let Some((numer, denom)) = s.split_once('/') {
+            let unsigned_numer = numer.strip_prefix(['+', '-']).unwrap_or(numer);
+            if !all_digits(unsigned_numer) || !all_digits(denom) {
+                return None;
+            }
+            let denom: BigInt = denom.parse().ok()?;
+            if denom.is_zero() {
+                return None;
+            }
+            return Some(Number::from_rational(BigRational::new(numer.parse().ok()?, denom)));
+        }
+
+        // Only accept float syntax, so names like `inf` and `nan` stay symbols
+        let starts_numeric = unsigned.starts_with(|c: char| c.is_ascii_digit())
+            || (unsigned.starts_with('.') && unsigned[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if starts_numeric {
+            return s.parse::<f64>().ok().map(Number::Real);
+        }
+        None
+    }
+
+    /// Builds the simplest exact number equal to `n`
+    pub fn from_big(n: BigInt) -> Number {
+        match n.to_i64() {
+            Some(n) => Number::Integer(n),
+            None => Number::Big(n),
+        }
+    }
+
+    /// Builds the simplest exact number equal to `r`
+    pub fn from_rational(r: BigRational) -> Number {
+        if r.is_integer() {
+            Number::from_big(r.to_integer())
+        } else {
+            Number::Rational(r)
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Number::Real(_))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Number::Integer(_) | Number::Big(_) => true,
+            Number::Rational(_) => false,
+            Number::Real(f) => f.is_finite() && f.fract() == 0.0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(n) => *n == 0,
+            Number::Big(n) => n.is_zero(),
+            Number::Rational(r) => r.is_zero(),
+            Number::Real(f) => *f == 0.0,
+        }
+    }
+
+    /// The nearest `f64` to this number
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(n) => *n as f64,
+            Number::Big(n) => n.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Real(f) => *f,
+        }
+    }
+
+    /// Converts to an inexact number
+    pub fn to_inexact(&self) -> Number {
+        Number::Real(self.to_f64())
+    }
+
+    /// Converts to the exact number with the same value
+    pub fn to_exact(&self) -> Result<Number, LispError> {
+        match self {
+            Number::Real(f) => BigRational::from_float(*f)
+                .map(Number::from_rational)
+                .ok_or_else(|| LispError::Custom(format!("inexact->exact: {} has no exact equivalent", self))),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Converts a non-negative exact integer to a `usize`
+    pub fn to_usize(&self) -> Option<usize> {
+        match self {
+            Number::Integer(n) => usize::try_from(*n).ok(),
+            Number::Big(n) => n.to_usize(),
+            _ => None,
+        }
+    }
+
+    /// Converts an exact integer that fits in an `i64`
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Number::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn to_big(&self) -> BigInt {
+        match self {
+            Number::Integer(n) => BigInt::from(*n),
+            Number::Big(n) => n.clone(),
+            _ => unreachable!("only called on exact integers"),
+        }
+    }
+
+    fn to_rational(&self) -> BigRational {
+        match self {
+            Number::Integer(n) => BigRational::from_integer(BigInt::from(*n)),
+            Number::Big(n) => BigRational::from_integer(n.clone()),
+            Number::Rational(r) => r.clone(),
+            Number::Real(_) => unreachable!("only called on exact numbers"),
+        }
+    }
+
+    /// The position of this representation in the tower; operands are
+    /// converted up to the higher of the two
+    fn level(&self) -> u8 {
+        match self {
+            Number::Integer(_) => 0,
+            Number::Big(_) => 1,
+            Number::Rational(_) => 2,
+            Number::Real(_) => 3,
+        }
+    }
+
+    fn coerce(&self, other: &Number) -> Operands {
+        match self.level().max(other.level()) {
+            0 => match (self, other) {
+                (Number::Integer(a), Number::Integer(b)) => Operands::Integers(*a, *b),
+                _ => unreachable!(),
+            },
+            1 => Operands::Bigs(self.to_big(), other.to_big()),
+            2 => Operands::Rationals(self.to_rational(), other.to_rational()),
+            _ => Operands::Reals(self.to_f64(), other.to_f64()),
+        }
+    }
+
+    /// Applies an operation at the common level of both operands, retrying
+    /// with bignums when an `i64` operation overflows
+    fn arithmetic(
+        &self,
+        other: &Number,
+        integers: fn(i64, i64) -> Option<i64>,
+        bigs: fn(BigInt, BigInt) -> BigInt,
+        rationals: fn(BigRational, BigRational) -> BigRational,
+        reals: fn(f64, f64) -> f64,
+    ) -> Number {
+        match self.coerce(other) {
+            Operands::Integers(a, b) => match integers(a, b) {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(bigs(BigInt::from(a), BigInt::from(b))),
+            },
+            Operands::Bigs(a, b) => Number::from_big(bigs(a, b)),
+            Operands::Rationals(a, b) => Number::from_rational(rationals(a, b)),
+            Operands::Reals(a, b) => Number::Real(reals(a, b)),
+        }
+    }
+
+    /// Divides, producing an exact rational when both operands are exact.
+    /// Only exact division by zero is an error; inexact division follows
+    /// IEEE 754 and gives an infinity or NaN.
+    pub fn divide(&self, other: &Number) -> Result<Number, LispError> {
+        match self.coerce(other) {
+            Operands::Reals(a, b) => Ok(Number::Real(a / b)),
+            _ if other.is_zero() => Err(LispError::DivisionByZero),
+            _ => Ok(Number::from_rational(self.to_rational() / other.to_rational())),
+        }
+    }
+
+    /// Integer division truncating towards zero
+    pub fn quotient(&self, other: &Number) -> Result<Number, LispError> {
+        self.integer_division(other, |a, b| a.checked_div(b), |a, b| a / b, |a, b| (a / b).trunc())
+    }
+
+    /// Remainder with the sign of the dividend
+    pub fn remainder(&self, other: &Number) -> Result<Number, LispError> {
+        self.integer_division(other, |a, b| a.checked_rem(b), |a, b| a % b, |a, b| a % b)
+    }
+
+    /// Remainder with the sign of the divisor
+    pub fn modulo(&self, other: &Number) -> Result<Number, LispError> {
+        self.integer_division(
+            other,
+            |a, b| a.checked_rem(b).map(|r| if r != 0 && (r < 0) != (b < 0) { r + b } else { r }),
+            |a, b| a.mod_floor(&b),
+            |a, b| a - b * (a / b).floor(),
+        )
+    }
+
+    fn integer_division(
+        &self,
+        other: &Number,
+        integers: fn(i64, i64) -> Option<i64>,
+        bigs: fn(BigInt, BigInt) -> BigInt,
+        reals: fn(f64, f64) -> f64,
+    ) -> Result<Number, LispError> {
+        for n in [self, other] {
+            if !n.is_integer() {
+                return Err(LispError::TypeMismatch {
+                    expected: "integer".to_string(),
+                    got: n.to_string(),
+                });
+            }
+        }
+        if other.is_zero() {
+            return Err(LispError::DivisionByZero);
+        }
+        Ok(match self.coerce(other) {
+            Operands::Integers(a, b) => match integers(a, b) {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(bigs(BigInt::from(a), BigInt::from(b))),
+            },
+            Operands::Bigs(a, b) => Number::from_big(bigs(a, b)),
+            Operands::Reals(a, b) => Number::Real(reals(a, b)),
+            Operands::Rationals(_, _) => unreachable!("integers never coerce to rationals"),
+        })
+    }
+
+    /// Raises this number to a power. Exact bases with exact integer
+    /// exponents give exact results; everything else is computed inexactly.
+    pub fn expt(&self, exponent: &Number) -> Result<Number, LispError> {
+        if !self.is_exact() || !exponent.is_exact() || !exponent.is_integer() {
+            return Ok(Number::Real(self.to_f64().powf(exponent.to_f64())));
+        }
+        let power = match exponent {
+            Number::Integer(n) => i32::try_from(*n).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| LispError::Custom(format!("expt: exponent {} is too large", exponent)))?;
+        if power < 0 && self.is_zero() {
+            return Err(LispError::DivisionByZero);
+        }
+        if let Number::Integer(base) = self
+            && let Ok(power) = u32::try_from(power)
+            && let Some(n) = base.checked_pow(power)
+        {
+            return Ok(Number::Integer(n));
+        }
+        Ok(Number::from_rational(self.to_rational().pow(power)))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Integer(n) => write!(f, "{}", n),
+            Number::Big(n) => write!(f, "{}", n),
+            Number::Rational(r) => write!(f, "{}/{}", r.numer(), r.denom()),
+            Number::Real(x) if x.is_nan() => write!(f, "+nan.0"),
+            Number::Real(x) if x.is_infinite() => {
+                write!(f, "{}inf.0", if x.is_sign_positive() { "+" } else { "-" })
+            }
+            // Debug formatting keeps a ".0" or exponent, so the output reads back as inexact
+            Number::Real(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        match self.coerce(other) {
+            Operands::Integers(a, b) => Some(a.cmp(&b)),
+            Operands::Bigs(a, b) => Some(a.cmp(&b)),
+            Operands::Rationals(a, b) => Some(a.cmp(&b)),
+            Operands::Reals(a, b) => a.partial_cmp(&b),
+        }
+    }
+}
+
+impl Add for &Number {
+    type Output = Number;
+
+    fn add(self, other: &Number) -> Number {
+        self.arithmetic(other, i64::checked_add, |a, b| a + b, |a, b| a + b, |a, b| a + b)
+    }
+}
+
+impl Sub for &Number {
+    type Output = Number;
+
+    fn sub(self, other: &Number) -> Number {
+        self.arithmetic(other, i64::checked_sub, |a, b| a - b, |a, b| a - b, |a, b| a - b)
+    }
+}
+
+impl Mul for &Number {
+    type Output = Number;
+
+    fn mul(self, other: &Number) -> Number {
+        self.arithmetic(other, i64::checked_mul, |a, b| a * b, |a, b| a * b, |a, b| a * b)
+    }
+}
+
+impl Neg for &Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        match self {
+            Number::Integer(n) => match n.checked_neg() {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(-BigInt::from(*n)),
+            },
+            Number::Big(n) => Number::from_big(-n),
+            Number::Rational(r) => Number::Rational(-r),
+            Number::Real(x) => Number::Real(-x),
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Number {
+        Number::Integer(n)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(x: f64) -> Number {
+        Number::Real(x)
+    }
+}
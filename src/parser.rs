@@ -1,88 +1,309 @@
 use std::rc::Rc;
 use crate::types::{Value, cons};
+use crate::number::Number;
+use crate::error::LispError;
+use crate::source::{Span, record_span};
 
-pub fn read(input: &str) -> Result<Rc<Value>, String> {
-    let mut chars = input.chars().peekable();
-    read_expr(&mut chars)
+/// Reads a single expression, attributing its source locations to `<input>`
+pub fn read(input: &str) -> Result<Rc<Value>, LispError> {
+    read_named(input, "<input>")
 }
 
-fn read_expr<I>(chars: &mut std::iter::Peekable<I>) -> Result<Rc<Value>, String>
-where
-    I: Iterator<Item = char>,
-{
-    // Skip whitespace
-    while let Some(&c) = chars.peek() {
-        if c.is_whitespace() {
-            chars.next();
-        } else {
-            break;
+/// Reads a single expression, attributing its source locations to `file`
+pub fn read_named(input: &str, file: &str) -> Result<Rc<Value>, LispError> {
+    Reader::new(input, file).read_expr()
+}
+
+/// Reads every expression in the input, attributing source locations to `<input>`
+pub fn read_all(input: &str) -> Result<Vec<Rc<Value>>, LispError> {
+    read_all_named(input, "<input>")
+}
+
+/// Reads every expression in the input, attributing source locations to `file`
+pub fn read_all_named(input: &str, file: &str) -> Result<Vec<Rc<Value>>, LispError> {
+    let mut reader = Reader::new(input, file);
+    reader.skip_shebang();
+    let mut exprs = Vec::new();
+    loop {
+        reader.skip_whitespace()?;
+        if reader.peek().is_none() {
+            return Ok(exprs);
         }
+        exprs.push(reader.read_expr()?);
     }
+}
 
-    match chars.peek() {
-        Some(&'(') => {
-            chars.next(); // Skip '('
-            read_list(chars)
-        }
-        Some(&'\'') => {
-            chars.next(); // Skip '\''
-            let expr = read_expr(chars)?;
-            Ok(cons(Rc::new(Value::Symbol("quote".to_string())), cons(expr, Rc::new(Value::Nil))))
+/// Walks the input character by character, keeping track of the current
+/// line and column so every form it builds can be given a span
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    file: Rc<str>,
+    source: Rc<str>,
+}
+
+impl Reader {
+    fn new(input: &str, file: &str) -> Self {
+        Reader {
+            chars: input.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+            file: file.into(),
+            source: input.into(),
         }
-        Some(_) => read_atom(chars),
-        None => Err("Unexpected end of input".to_string()),
     }
-}
 
-fn read_list<I>(chars: &mut std::iter::Peekable<I>) -> Result<Rc<Value>, String>
-where
-    I: Iterator<Item = char>,
-{
-    // Skip whitespace
-    while let Some(&c) = chars.peek() {
-        if c.is_whitespace() {
-            chars.next();
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            break;
+            self.column += 1;
         }
+        Some(c)
     }
 
-    match chars.peek() {
-        Some(&')') => {
-            chars.next(); // Skip ')'
-            Ok(Rc::new(Value::Nil))
+    /// The span of the next character to be read
+    fn span(&self) -> Span {
+        Span::new(self.file.clone(), self.source.clone(), self.line, self.column)
+    }
+
+    fn error(&self, message: &str, span: Span) -> LispError {
+        LispError::Syntax(message.to_string()).with_span(span)
+    }
+
+    /// An error for input that stops partway through a form
+    fn incomplete(&self, message: &str, span: Span) -> LispError {
+        LispError::Incomplete(message.to_string()).with_span(span)
+    }
+
+    /// Skips whitespace and comments: `;` to the end of the line,
+    /// nestable `#| ... |#` blocks and `#;` followed by the datum it discards
+    fn skip_whitespace(&mut self) -> Result<(), LispError> {
+        while let Some(c) = self.peek() {
+            match (c, self.chars.get(self.pos + 1)) {
+                (c, _) if c.is_whitespace() => {
+                    self.next();
+                }
+                (';', _) => {
+                    while let Some(c) = self.next() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                ('#', Some('|')) => self.skip_block_comment()?,
+                ('#', Some(';')) => {
+                    self.next(); // Skip '#'
+                    self.next(); // Skip ';'
+                    self.read_expr()?;
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), LispError> {
+        let open = self.span();
+        self.next(); // Skip '#'
+        self.next(); // Skip '|'
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.next(), self.peek()) {
+                (Some('#'), Some('|')) => {
+                    self.next();
+                    depth += 1;
+                }
+                (Some('|'), Some('#')) => {
+                    self.next();
+                    depth -= 1;
+                }
+                (Some(_), _) => {}
+                (None, _) => return Err(self.incomplete("Expected '|#' but got end of input", open)),
+            }
         }
-        Some(_) => {
-            let car = read_expr(chars)?;
-            let cdr = read_list(chars)?;
-            Ok(cons(car, cdr))
+        Ok(())
+    }
+
+    /// Skips a `#!` interpreter line at the very start of a script
+    fn skip_shebang(&mut self) {
+        if self.pos == 0 && self.chars.starts_with(&['#', '!']) {
+            while let Some(c) = self.next() {
+                if c == '\n' {
+                    break;
+                }
+            }
         }
-        None => Err("Expected ')' but got end of input".to_string()),
     }
-}
 
-fn read_atom<I>(chars: &mut std::iter::Peekable<I>) -> Result<Rc<Value>, String>
-where
-    I: Iterator<Item = char>,
-{
-    let mut atom = String::new();
-    
-    while let Some(&c) = chars.peek() {
-        if c.is_whitespace() || c == '(' || c == ')' {
-            break;
+    fn read_expr(&mut self) -> Result<Rc<Value>, LispError> {
+        self.skip_whitespace()?;
+
+        let start = self.span();
+        let expr = match self.peek() {
+            Some('(') => {
+                self.next(); // Skip '('
+                self.read_list(&start)?
+            }
+            Some('\'') => {
+                self.next(); // Skip '\''
+                self.read_prefixed("quote")?
+            }
+            Some('`') => {
+                self.next(); // Skip '`'
+                self.read_prefixed("quasiquote")?
+            }
+            Some(',') => {
+                self.next(); // Skip ','
+                if self.peek() == Some('@') {
+                    self.next(); // Skip '@'
+                    self.read_prefixed("unquote-splicing")?
+                } else {
+                    self.read_prefixed("unquote")?
+                }
+            }
+            Some('"') => {
+                self.next(); // Skip '"'
+                self.read_string(&start)?
+            }
+            Some(_) => self.read_atom()?,
+            None => return Err(self.incomplete("Unexpected end of input", start)),
+        };
+        record_span(&expr, start);
+        Ok(expr)
+    }
+
+    /// Reads the expression after a reader prefix such as `'` and wraps it as `(name expr)`
+    fn read_prefixed(&mut self, name: &str) -> Result<Rc<Value>, LispError> {
+        let expr = self.read_expr()?;
+        Ok(cons(Rc::new(Value::Symbol(name.to_string())), cons(expr, Rc::new(Value::Nil))))
+    }
+
+    /// Reads list elements up to the closing parenthesis. `open` is the
+    /// location of the opening parenthesis, reported if it is never closed.
+    /// A `.` before the last element makes the list improper: `(a b . c)`.
+    fn read_list(&mut self, open: &Span) -> Result<Rc<Value>, LispError> {
+        let mut items = Vec::new();
+        let mut tail = Rc::new(Value::Nil);
+        loop {
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(')') => {
+                    self.next(); // Skip ')'
+                    break;
+                }
+                Some('.') if self.at_dot() => {
+                    let dot = self.span();
+                    self.next(); // Skip '.'
+                    if items.is_empty() {
+                        return Err(self.error("Unexpected '.' at the start of a list", dot));
+                    }
+                    tail = self.read_dotted_tail(open)?;
+                    break;
+                }
+                Some(_) => items.push(self.read_expr()?),
+                None => return Err(self.incomplete("Expected ')' but got end of input", open.clone())),
+            }
         }
-        atom.push(c);
-        chars.next();
+        Ok(items.into_iter().rev().fold(tail, |list, item| cons(item, list)))
     }
-    
-    if atom.is_empty() {
-        return Err("Expected atom but got nothing".to_string());
+
+    /// Reads the single form after the `.` of a dotted list and its closing parenthesis
+    fn read_dotted_tail(&mut self, open: &Span) -> Result<Rc<Value>, LispError> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(')') => return Err(self.error("Expected a form after '.'", self.span())),
+            None => return Err(self.incomplete("Expected ')' but got end of input", open.clone())),
+            Some(_) => {}
+        }
+        let tail = self.read_expr()?;
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(')') => {
+                self.next(); // Skip ')'
+                Ok(tail)
+            }
+            Some(_) => Err(self.error("Expected ')' after the form following '.'", self.span())),
+            None => Err(self.incomplete("Expected ')' but got end of input", open.clone())),
+        }
     }
-    
-    // Try to parse as number
-    if let Ok(n) = atom.parse::<f64>() {
-        Ok(Rc::new(Value::Number(n)))
-    } else {
-        Ok(Rc::new(Value::Symbol(atom)))
+
+    /// Whether the next character is a `.` on its own rather than the start of
+    /// an atom such as `...` or `.5`
+    fn at_dot(&self) -> bool {
+        self.peek() == Some('.') && self.chars.get(self.pos + 1).is_none_or(|&c| is_delimiter(c))
+    }
+
+    fn read_string(&mut self, open: &Span) -> Result<Rc<Value>, LispError> {
+        let mut string = String::new();
+
+        loop {
+            let escape = self.span();
+            match self.next() {
+                Some('"') => return Ok(Rc::new(Value::String(string))),
+                Some('\\') => match self.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    Some('0') => string.push('\0'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some(c) => {
+                        let message = format!("Unknown escape sequence '\\{}' in string", c);
+                        return Err(self.error(&message, escape));
+                    }
+                    None => return Err(self.incomplete("Expected '\"' but got end of input", open.clone())),
+                },
+                Some(c) => string.push(c),
+                None => return Err(self.incomplete("Expected '\"' but got end of input", open.clone())),
+            }
+        }
     }
+
+    fn read_atom(&mut self) -> Result<Rc<Value>, LispError> {
+        let start = self.span();
+        let mut atom = String::new();
+
+        while let Some(c) = self.peek() {
+            if is_delimiter(c) {
+                break;
+            }
+            atom.push(c);
+            self.next();
+        }
+
+        if atom == "." {
+            return Err(self.error("Unexpected '.' outside a list", start));
+        }
+
+        if atom.is_empty() {
+            let message = match self.peek() {
+                Some(c) => format!("Unexpected '{}'", c),
+                None => "Expected atom but got nothing".to_string(),
+            };
+            return Err(self.error(&message, start));
+        }
+
+        // Try to parse as number
+        if let Some(n) = Number::parse(&atom) {
+            Ok(Rc::new(Value::Number(n)))
+        } else {
+            Ok(Rc::new(Value::Symbol(atom)))
+        }
+    }
+}
+
+/// Characters that end an atom
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"' || c == ';'
 }
@@ -1,43 +1,86 @@
+use std::collections::HashSet;
 use std::rc::Rc;
-use crate::types::Value;
+use crate::types::{Value, car, cdr};
 
+/// Renders a value as text. A pair that contains itself is printed as `...`
+/// where it recurs, so circular lists built with `set-cdr!` still print.
 pub fn print_value(value: &Rc<Value>) -> String {
-    match &**value {
+    let mut result = String::new();
+    write_value(value, &mut HashSet::new(), &mut result);
+    result
+}
+
+/// Appends `value` to `result`. `open` holds the pairs whose printing is in
+/// progress; reaching one of them again means the structure is circular.
+fn write_value(value: &Rc<Value>, open: &mut HashSet<*const Value>, result: &mut String) {
+    let atom = match &**value {
         Value::Nil => "()".to_string(),
         Value::Bool(true) => "#t".to_string(),
         Value::Bool(false) => "#f".to_string(),
-        Value::Number(n) => format!("{}", n),
+        Value::Number(n) => n.to_string(),
         Value::Symbol(s) => s.clone(),
+        Value::String(s) => escape_string(s),
         Value::Procedure(name, _) => format!("<procedure:{}>", name),
+        Value::SpecialForm(name, _) => format!("<special-form:{}>", name),
         Value::Lambda(_, _, _) => "<lambda>".to_string(),
+        Value::Macro(_, _, _) => "<macro>".to_string(),
+        Value::Foreign(foreign) => foreign.to_string(),
+        Value::Unassigned => "<unassigned>".to_string(),
         Value::Cons(_, _) => {
-            let mut result = String::from("(");
-            let mut is_first = true;
-            let mut current = value.clone();
-            
-            while let Value::Cons(car, cdr) = &*current {
-                if !is_first {
-                    result.push_str(" ");
-                }
-                result.push_str(&print_value(car));
-                is_first = false;
-                
-                match &**cdr {
-                    Value::Nil => {},
-                    Value::Cons(_, _) => {
-                        current = cdr.clone();
-                        continue;
-                    },
-                    _ => {
-                        result.push_str(" . ");
-                        result.push_str(&print_value(cdr));
-                    }
-                }
+            if open.contains(&Rc::as_ptr(value)) {
+                result.push_str("...");
+                return;
+            }
+            write_list(value, open, result);
+            return;
+        }
+    };
+    result.push_str(&atom);
+}
+
+fn write_list(list: &Rc<Value>, open: &mut HashSet<*const Value>, result: &mut String) {
+    result.push('(');
+    let mut spine = Vec::new();
+    let mut current = list.clone();
+    loop {
+        open.insert(Rc::as_ptr(&current));
+        spine.push(Rc::as_ptr(&current));
+        write_value(&car(&current), open, result);
+
+        let rest = cdr(&current);
+        match &*rest {
+            Value::Nil => break,
+            Value::Cons(_, _) if !open.contains(&Rc::as_ptr(&rest)) => {
+                result.push(' ');
+                current = rest;
+            }
+            _ => {
+                result.push_str(" . ");
+                write_value(&rest, open, result);
                 break;
             }
-            
-            result.push_str(")");
-            result
         }
     }
+    result.push(')');
+    for pair in spine {
+        open.remove(&pair);
+    }
+}
+
+/// Renders a string as a literal that the reader parses back to the same string
+fn escape_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
 }
@@ -0,0 +1,114 @@
+//! The `:commands` the REPL accepts, kept apart from the line editor so
+//! they can write to any output.
+
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::Instant;
+use crate::types::Value;
+use crate::error::LispError;
+use crate::eval::macroexpand;
+use crate::interpreter::Interpreter;
+use crate::environment::documentation;
+use crate::parser::read_all_named;
+use crate::printer::print_value;
+
+/// What `:help` shows
+pub const REPL_HELP: &str = "\
+:help           show this message
+:env            list the bindings in the environment and their kinds
+:doc <symbol>   describe what a symbol is bound to
+:load <file>    evaluate every form in a file
+:reset          discard all definitions and start from a fresh environment
+:time <expr>    evaluate expressions and report how long they took
+:expand <expr>  show the full macro expansion of a form
+:type <expr>    evaluate an expression and show the kind of its value
+exit            leave the REPL";
+
+/// A short name for the kind of a value, as shown by `:env` and `:type`
+pub fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Nil => "nil",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::Symbol(_) => "symbol",
+        Value::String(_) => "string",
+        Value::Cons(_, _) => "pair",
+        Value::Procedure(_, _) => "procedure",
+        Value::SpecialForm(_, _) => "special form",
+        Value::Lambda(_, _, _) => "lambda",
+        Value::Macro(_, _, _) => "macro",
+        Value::Foreign(_) => "foreign",
+        Value::Unassigned => "unassigned",
+    }
+}
+
+/// Runs a `:command` line, writing what it shows to `out`.
+/// `:reset` replaces `interp` with a fresh interpreter with the same settings.
+pub fn meta_command(line: &str, interp: &mut Interpreter, out: &mut impl Write) -> Result<(), LispError> {
+    let env = interp.env().clone();
+    let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+    match command {
+        ":help" => writeln!(out, "{}", REPL_HELP).map_err(output_error)?,
+        ":env" => {
+            for name in env.names() {
+                if let Some(value) = env.get(&name) {
+                    writeln!(out, "{:<24} {}", name, kind(&value)).map_err(output_error)?;
+                }
+            }
+        }
+        ":doc" => {
+            let value = env.get(arg).ok_or_else(|| LispError::UnboundSymbol(arg.to_string()))?;
+            match &*value {
+                Value::Lambda(params, _, _) | Value::Macro(params, _, _) => {
+                    writeln!(out, "{}: {} {}", arg, kind(&value), print_value(params)).map_err(output_error)?;
+                }
+                _ => writeln!(out, "{}: {}", arg, kind(&value)).map_err(output_error)?,
+            }
+            if let Some(doc) = documentation(&env, arg) {
+                for line in doc.lines() {
+                    writeln!(out, "  {}", line).map_err(output_error)?;
+                }
+            }
+        }
+        ":load" => {
+            interp.eval_file(arg)?;
+            writeln!(out, "Loaded {}", arg).map_err(output_error)?;
+        }
+        ":reset" => {
+            *interp = Interpreter::with_config(interp.config().clone());
+            writeln!(out, "Environment reset").map_err(output_error)?;
+        }
+        ":time" => {
+            let start = Instant::now();
+            let mut result = Rc::new(Value::Nil);
+            for expr in read_all_named(arg, &interp.config().source_name)? {
+                result = interp.eval(expr)?;
+            }
+            writeln!(out, "{}", print_value(&result)).map_err(output_error)?;
+            writeln!(out, "Elapsed: {:?}", start.elapsed()).map_err(output_error)?;
+        }
+        ":expand" => {
+            for expr in read_all_named(arg, &interp.config().source_name)? {
+                writeln!(out, "{}", print_value(&macroexpand(expr, &env)?)).map_err(output_error)?;
+            }
+        }
+        ":type" => {
+            for expr in read_all_named(arg, &interp.config().source_name)? {
+                let value = interp.eval(expr)?;
+                writeln!(out, "{}", kind(&value)).map_err(output_error)?;
+            }
+        }
+        _ => {
+            return Err(LispError::Custom(format!(
+                "Unknown command {}, type :help for a list",
+                command
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn output_error(err: io::Error) -> LispError {
+    LispError::Custom(format!("Cannot write output: {}", err))
+}
@@ -1,17 +1,31 @@
 use std::rc::Rc;
 use std::collections::HashMap;
 use std::cell::RefCell;
-use crate::types::{Value, Environment, car, cdr};
+use std::fmt;
+use crate::number::Number;
+use crate::types::{Value, Environment, NativeFn, Tail, car, cdr};
 use crate::eval::eval_list;
+use crate::error::{LispError, LispResult};
+use crate::printer::print_value;
+use crate::interop::lisp_to_rust_vector;
+use crate::convert::{FromLisp, IntoLisp};
 
-/// Type definition for Rust functions that can be called from Lisp
-pub type RustFunction = fn(Vec<Rc<Value>>) -> Rc<Value>;
+/// Type definition for Rust functions that can be called from Lisp.
+/// Closures may capture host state such as handles, configuration or counters.
+/// An `Err` becomes a Lisp error at the call site.
+pub type RustFunction = Rc<dyn Fn(Vec<Rc<Value>>) -> LispResult>;
 
-/// Global registry of Rust functions that can be called from Lisp
+/// Registry of Rust functions that can be called from Lisp through `rust-call`
 pub struct RustFunctionRegistry {
     functions: RefCell<HashMap<String, RustFunction>>,
 }
 
+impl Default for RustFunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RustFunctionRegistry {
     /// Create a new empty registry
     pub fn new() -> Self {
@@ -29,132 +43,240 @@ impl RustFunctionRegistry {
     pub fn get(&self, name: &str) -> Option<RustFunction> {
         self.functions.borrow().get(name).cloned()
     }
+
+    /// The names of all registered functions, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.borrow().keys().cloned().collect();
+        names.sort();
+        names
+    }
 }
 
-// Create a thread-local registry
+// The registry shared by the free functions below. Each `Interpreter` has its own instead.
 thread_local! {
-    static RUST_FUNCTIONS: RustFunctionRegistry = RustFunctionRegistry::new();
+    static DEFAULT_REGISTRY: Rc<RustFunctionRegistry> = Rc::new(RustFunctionRegistry::new());
+}
+
+/// The registry that `register_rust_function`, `rust_call` and
+/// `setup_rust_functions` use, shared by all their callers on this thread
+pub fn default_registry() -> Rc<RustFunctionRegistry> {
+    DEFAULT_REGISTRY.with(Rc::clone)
+}
+
+/// Register a Rust function or closure in the default registry, to be
+/// callable from Lisp through `rust-call`
+pub fn register_rust_function(name: &str, func: impl Fn(Vec<Rc<Value>>) -> LispResult + 'static) {
+    default_registry().register(name, Rc::new(func));
+}
+
+/// The names of all Rust functions registered with `register_rust_function`
+pub fn rust_function_names() -> Vec<String> {
+    default_registry().names()
+}
+
+/// Bind a Rust function or closure in `env` as an ordinary procedure, so
+/// scripts can call it as `(name args...)`, pass it to other functions and
+/// store it in data structures like any other value
+pub fn define_rust_function(
+    env: &Rc<Environment>,
+    name: &str,
+    func: impl Fn(Vec<Rc<Value>>) -> LispResult + 'static,
+) {
+    env.define(name, rust_procedure(name, func));
 }
 
-/// Register a Rust function to be callable from Lisp
-pub fn register_rust_function(name: &str, func: RustFunction) {
-    RUST_FUNCTIONS.with(|registry| {
-        registry.register(name, func);
-    });
+/// Wrap a Rust function or closure as a procedure value
+pub fn rust_procedure(name: &str, func: impl Fn(Vec<Rc<Value>>) -> LispResult + 'static) -> Rc<Value> {
+    Value::procedure(name, move |args, _| func(lisp_to_rust_vector(&args)))
 }
 
 /// Call a Rust function from Lisp
-/// This is the implementation of the 'rust-call' special form
-pub fn rust_call(args: Rc<Value>, env: Rc<Environment>) -> Rc<Value> {
+/// This is the implementation of the 'rust-call' special form set up by `setup_rust_functions`
+pub fn rust_call(args: Rc<Value>, env: Rc<Environment>) -> LispResult {
+    rust_call_with(&default_registry(), args, env)
+}
+
+/// Call a Rust function from `registry`, or a procedure bound in `env`, from Lisp
+pub fn rust_call_with(registry: &RustFunctionRegistry, args: Rc<Value>, env: Rc<Environment>) -> LispResult {
     // First argument should be the function name
-    if let Value::Symbol(func_name) = &*car(&args) {
-        // Evaluate the rest of the arguments
-        let eval_args = eval_list(cdr(&args), env);
-
-        // Convert the arguments to a Vec
-        let mut arg_vec = Vec::new();
-        let mut current = eval_args;
-        while let Value::Cons(car_val, cdr_val) = &*current {
-            arg_vec.push(car_val.clone());
-            current = cdr_val.clone();
+    let func_name = match &*car(&args) {
+        Value::Symbol(name) => name.clone(),
+        _ => {
+            return Err(LispError::TypeMismatch {
+                expected: "function name".to_string(),
+                got: print_value(&car(&args)),
+            });
         }
+    };
 
-        // Look up the function in the registry
-        let mut result = Rc::new(Value::Nil);
-        RUST_FUNCTIONS.with(|registry| {
-            if let Some(func) = registry.get(func_name) {
-                // Call the function with the arguments
-                result = func(arg_vec);
-            } else {
-                println!("Rust function '{}' not found", func_name);
-            }
-        });
-        return result;
-    }
+    // Look up the function in the registry, then among the procedures
+    // bound with `define_rust_function`
+    let func: NativeFn = match registry.get(&func_name) {
+        Some(func) => Rc::new(move |args, _| func(lisp_to_rust_vector(&args))),
+        None => match env.get(&func_name).as_deref() {
+            Some(Value::Procedure(_, f)) => f.clone(),
+            _ => return Err(LispError::Custom(format!("Rust function '{}' not found", func_name))),
+        },
+    };
 
-    println!("Expected function name as first argument");
-    Rc::new(Value::Nil)
+    // Evaluate the rest of the arguments and call the function with them
+    let eval_args = eval_list(cdr(&args), env.clone())?;
+    func(eval_args, env)
 }
 
-/// Setup the environment with the 'rust-call' special form
+/// Setup the environment with the 'rust-call' special form, calling
+/// functions registered with `register_rust_function`
 pub fn setup_rust_functions(env: Rc<Environment>) {
-    env.borrow_mut().insert(
-        "rust-call".to_string(),
-        Rc::new(Value::Procedure("rust-call".to_string(), rust_call))
+    install_rust_call(&env, default_registry());
+}
+
+/// Define the 'rust-call' special form in `env`, calling functions from `registry`
+pub fn install_rust_call(env: &Rc<Environment>, registry: Rc<RustFunctionRegistry>) {
+    env.define(
+        "rust-call",
+        Value::special_form("rust-call", move |args, env| {
+            rust_call_with(&registry, args, env).map(Tail::Return)
+        })
     );
 }
 
-// Example Rust functions that can be called from Lisp
+/// The remaining arguments of a variadic typed function, each converted to `T`.
+/// It may only appear as the last parameter.
+pub struct Rest<T>(pub Vec<T>);
+
+/// What a typed Rust function may return: any `IntoLisp` value, or a
+/// `Result` whose error becomes a Lisp error
+pub trait IntoLispResult {
+    fn into_lisp_result(self) -> LispResult;
+}
 
-/// A simple Rust function that adds two numbers
-pub fn rust_add(args: Vec<Rc<Value>>) -> Rc<Value> {
-    if args.len() < 2 {
-        println!("rust-add requires at least two arguments");
-        return Rc::new(Value::Nil);
+impl<T: IntoLisp> IntoLispResult for T {
+    fn into_lisp_result(self) -> LispResult {
+        Ok(self.into_lisp())
     }
+}
 
-    let mut result = 0.0;
-    for arg in args {
-        if let Value::Number(n) = &*arg {
-            result += n;
-        } else {
-            println!("rust-add requires numeric arguments");
-            return Rc::new(Value::Nil);
-        }
+impl<T: IntoLisp, E: fmt::Display> IntoLispResult for Result<T, E> {
+    fn into_lisp_result(self) -> LispResult {
+        self.map(IntoLisp::into_lisp)
+            .map_err(|err| LispError::Custom(err.to_string()))
     }
+}
 
-    Rc::new(Value::Number(result))
+/// A Rust function with typed parameters that can be wrapped as a procedure.
+///
+/// The wrapper checks the number of arguments, converts each one with
+/// `FromLisp`, and converts the result back with `IntoLisp`. `Args` is the
+/// tuple of parameter types and is inferred from the function.
+pub trait TypedFunction<Args> {
+    fn into_native(self, name: &str) -> NativeFn;
 }
 
-/// A Rust function that multiplies two numbers
-pub fn rust_multiply(args: Vec<Rc<Value>>) -> Rc<Value> {
-    if args.len() < 2 {
-        println!("rust-multiply requires at least two arguments");
-        return Rc::new(Value::Nil);
-    }
+/// Converts argument `index` of `name`, saying which argument was wrong if it fails
+fn convert_arg<T: FromLisp>(name: &str, index: usize, arg: &Rc<Value>) -> Result<T, LispError> {
+    T::from_lisp(arg).map_err(|err| LispError::Argument {
+        function: name.to_string(),
+        index: index + 1,
+        error: Box::new(err),
+    })
+}
 
-    let mut result = 1.0;
-    for arg in args {
-        if let Value::Number(n) = &*arg {
-            result *= n;
+fn check_arg_count(name: &str, args: &[Rc<Value>], required: usize, variadic: bool) -> Result<(), LispError> {
+    if args.len() == required || (variadic && args.len() > required) {
+        return Ok(());
+    }
+    Err(LispError::ArityMismatch {
+        name: name.to_string(),
+        expected: if variadic {
+            format!("at least {}", required)
         } else {
-            println!("rust-multiply requires numeric arguments");
-            return Rc::new(Value::Nil);
+            required.to_string()
+        },
+        got: args.len(),
+    })
+}
+
+macro_rules! typed_functions {
+    ($(($len:expr; $($A:ident $i:tt),*)),*) => {$(
+        impl<Func, R, $($A),*> TypedFunction<($($A,)*)> for Func
+        where
+            Func: Fn($($A),*) -> R + 'static,
+            R: IntoLispResult,
+            $($A: FromLisp,)*
+        {
+            #[allow(unused_variables)]
+            fn into_native(self, name: &str) -> NativeFn {
+                let name = name.to_string();
+                Rc::new(move |args, _| {
+                    let args = lisp_to_rust_vector(&args);
+                    check_arg_count(&name, &args, $len, false)?;
+                    self($(convert_arg::<$A>(&name, $i, &args[$i])?),*).into_lisp_result()
+                })
+            }
         }
-    }
 
-    Rc::new(Value::Number(result))
+        impl<Func, R, T, $($A),*> TypedFunction<($($A,)* Rest<T>,)> for Func
+        where
+            Func: Fn($($A,)* Rest<T>) -> R + 'static,
+            R: IntoLispResult,
+            T: FromLisp,
+            $($A: FromLisp,)*
+        {
+            fn into_native(self, name: &str) -> NativeFn {
+                let name = name.to_string();
+                Rc::new(move |args, _| {
+                    let args = lisp_to_rust_vector(&args);
+                    check_arg_count(&name, &args, $len, true)?;
+                    let rest = args[$len..]
+                        .iter()
+                        .enumerate()
+                        .map(|(i, arg)| convert_arg::<T>(&name, $len + i, arg))
+                        .collect::<Result<_, _>>()?;
+                    self($(convert_arg::<$A>(&name, $i, &args[$i])?,)* Rest(rest)).into_lisp_result()
+                })
+            }
+        }
+    )*};
 }
 
-/// A Rust function that returns the length of a list
-pub fn rust_length(args: Vec<Rc<Value>>) -> Rc<Value> {
-    if args.len() != 1 {
-        println!("rust-length requires exactly one argument");
-        return Rc::new(Value::Nil);
-    }
+typed_functions!(
+    (0;),
+    (1; A 0),
+    (2; A 0, B 1),
+    (3; A 0, B 1, C 2),
+    (4; A 0, B 1, C 2, D 3),
+    (5; A 0, B 1, C 2, D 3, E 4),
+    (6; A 0, B 1, C 2, D 3, E 4, F 5)
+);
 
-    let mut count = 0;
-    let mut current = args[0].clone();
+/// Wrap a typed Rust function, such as `fn(f64, f64) -> f64`, as a procedure value
+pub fn typed_procedure<Args>(name: &str, func: impl TypedFunction<Args>) -> Rc<Value> {
+    Rc::new(Value::Procedure(name.to_string(), func.into_native(name)))
+}
 
-    while let Value::Cons(_, cdr_val) = &*current {
-        count += 1;
-        current = cdr_val.clone();
-    }
+/// Bind a typed Rust function in `env` as an ordinary procedure
+pub fn define_typed_function<Args>(env: &Rc<Environment>, name: &str, func: impl TypedFunction<Args>) {
+    env.define(name, typed_procedure(name, func));
+}
 
-    Rc::new(Value::Number(count as f64))
+// Example Rust functions that can be called from Lisp
+
+/// A simple Rust function that adds two or more numbers
+pub fn rust_add(first: Number, second: Number, rest: Rest<Number>) -> Number {
+    rest.0.iter().fold(&first + &second, |sum, n| &sum + n)
 }
 
-/// A Rust function that converts a string to uppercase
-pub fn rust_uppercase(args: Vec<Rc<Value>>) -> Rc<Value> {
-    if args.len() != 1 {
-        println!("rust-uppercase requires exactly one argument");
-        return Rc::new(Value::Nil);
-    }
+/// A Rust function that multiplies two or more numbers
+pub fn rust_multiply(first: Number, second: Number, rest: Rest<Number>) -> Number {
+    rest.0.iter().fold(&first * &second, |product, n| &product * n)
+}
 
-    if let Value::Symbol(s) = &*args[0] {
-        return Rc::new(Value::Symbol(s.to_uppercase()));
-    }
+/// A Rust function that returns the length of a list
+pub fn rust_length(list: Vec<Rc<Value>>) -> usize {
+    list.len()
+}
 
-    println!("rust-uppercase requires a symbol argument");
-    Rc::new(Value::Nil)
+/// A Rust function that converts a string to uppercase
+pub fn rust_uppercase(s: String) -> String {
+    s.to_uppercase()
 }
@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use crate::types::Value;
+
+/// The position in the source text where a form was read
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+    source: Rc<str>,
+}
+
+impl Span {
+    pub fn new(file: Rc<str>, source: Rc<str>, line: usize, column: usize) -> Self {
+        Span { file, line, column, source }
+    }
+
+    /// The full text of the line this span starts on
+    pub fn source_line(&self) -> &str {
+        self.source.lines().nth(self.line - 1).unwrap_or("")
+    }
+
+    /// The source line followed by a caret under this span's column
+    pub fn snippet(&self) -> String {
+        let line = self.source_line();
+        let indent: String = line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!("    {}\n    {}^", line, indent)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Side table from parsed values to the spans they were read from.
+///
+/// Entries are keyed by the address of the value and hold a weak reference,
+/// so a lookup only succeeds while the value it was recorded for is alive.
+#[derive(Default)]
+struct SpanTable {
+    entries: HashMap<*const Value, (Weak<Value>, Span)>,
+    prune_at: usize,
+}
+
+const MIN_PRUNE_AT: usize = 4096;
+
+thread_local! {
+    static SPANS: RefCell<SpanTable> = RefCell::new(SpanTable::default());
+}
+
+/// Remember that `value` was read from `span`
+pub fn record_span(value: &Rc<Value>, span: Span) {
+    SPANS.with(|table| {
+        let mut table = table.borrow_mut();
+        if table.entries.len() >= table.prune_at.max(MIN_PRUNE_AT) {
+            // Drop entries for values that no longer exist
+            table.entries.retain(|_, (weak, _)| weak.strong_count() > 0);
+            table.prune_at = table.entries.len() * 2;
+        }
+        table.entries.insert(Rc::as_ptr(value), (Rc::downgrade(value), span));
+    });
+}
+
+/// The span `value` was read from, if it came from the reader
+pub fn span_of(value: &Rc<Value>) -> Option<Span> {
+    SPANS.with(|table| {
+        let table = table.borrow();
+        let (weak, span) = table.entries.get(&Rc::as_ptr(value))?;
+        weak.upgrade()
+            .filter(|recorded| Rc::ptr_eq(recorded, value))
+            .map(|_| span.clone())
+    })
+}
@@ -1,34 +1,286 @@
+use std::any::TypeId;
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
+use crate::error::{LispError, LispResult};
+use crate::number::Number;
+use crate::gc;
+use crate::foreign::Foreign;
+use crate::printer::print_value;
 
-#[derive(Debug, Clone)]
+/// A procedure implemented in Rust. It may be a closure over host state.
+pub type NativeFn = Rc<dyn Fn(Rc<Value>, Rc<Environment>) -> LispResult>;
+
+/// A special form implemented in Rust, which receives its arguments unevaluated
+pub type NativeForm = Rc<dyn Fn(Rc<Value>, Rc<Environment>) -> Result<Tail, LispError>>;
+
+#[derive(Clone)]
 pub enum Value {
     Nil,
     Bool(bool),
-    Number(f64),
+    Number(Number),
     Symbol(String),
-    Cons(Rc<Value>, Rc<Value>),
-    Procedure(String, fn(Rc<Value>, Rc<Environment>) -> Rc<Value>),
+    String(String),
+    Cons(RefCell<Rc<Value>>, RefCell<Rc<Value>>),
+    Procedure(String, NativeFn),
+    SpecialForm(String, NativeForm),
     Lambda(Rc<Value>, Rc<Value>, Rc<Environment>),
+    Macro(Rc<Value>, Rc<Value>, Rc<Environment>),
+    Foreign(Foreign),
+    /// What a `letrec` variable holds until its init has been evaluated
+    Unassigned,
+}
+
+impl std::fmt::Debug for Value {
+    // Native procedures are opaque, so only their names are printed. The
+    // halves of a pair are rendered by the printer, which stops at cycles.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "Nil"),
+            Value::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Value::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            Value::Symbol(s) => f.debug_tuple("Symbol").field(s).finish(),
+            Value::String(s) => f.debug_tuple("String").field(s).finish(),
+            Value::Cons(car, cdr) => f
+                .debug_tuple("Cons")
+                .field(&format_args!("{}", print_value(&car.borrow())))
+                .field(&format_args!("{}", print_value(&cdr.borrow())))
+                .finish(),
+            Value::Procedure(name, _) => f.debug_tuple("Procedure").field(name).finish(),
+            Value::SpecialForm(name, _) => f.debug_tuple("SpecialForm").field(name).finish(),
+            Value::Lambda(params, body, env) => {
+                f.debug_tuple("Lambda").field(params).field(body).field(env).finish()
+            }
+            Value::Macro(params, body, env) => {
+                f.debug_tuple("Macro").field(params).field(body).field(env).finish()
+            }
+            Value::Foreign(foreign) => foreign.fmt(f),
+            Value::Unassigned => write!(f, "Unassigned"),
+        }
+    }
+}
+
+impl Value {
+    /// Create a procedure from a Rust function or closure, which receives its
+    /// evaluated arguments as a list along with the caller's environment
+    pub fn procedure(
+        name: &str,
+        f: impl Fn(Rc<Value>, Rc<Environment>) -> LispResult + 'static,
+    ) -> Rc<Value> {
+        Rc::new(Value::Procedure(name.to_string(), Rc::new(f)))
+    }
+
+    /// Create a special form from a Rust function or closure, which receives
+    /// its arguments unevaluated and returns a value or a form to evaluate next
+    pub fn special_form(
+        name: &str,
+        f: impl Fn(Rc<Value>, Rc<Environment>) -> Result<Tail, LispError> + 'static,
+    ) -> Rc<Value> {
+        Rc::new(Value::SpecialForm(name.to_string(), Rc::new(f)))
+    }
+
+    /// Create a closure over `env`, registering `env` with the garbage collector
+    pub fn new_lambda(params: Rc<Value>, body: Rc<Value>, env: Rc<Environment>) -> Rc<Value> {
+        gc::track(&env);
+        Rc::new(Value::Lambda(params, body, env))
+    }
+
+    /// Create a macro closed over `env`, registering `env` with the garbage collector
+    pub fn new_macro(params: Rc<Value>, body: Rc<Value>, env: Rc<Environment>) -> Rc<Value> {
+        gc::track(&env);
+        Rc::new(Value::Macro(params, body, env))
+    }
 }
 
-pub type Environment = RefCell<HashMap<String, Rc<Value>>>;
+/// What a special form hands back to the evaluator
+#[derive(Debug, Clone)]
+pub enum Tail {
+    /// The form has been fully evaluated to this value
+    Return(Rc<Value>),
+    /// The form continues by evaluating this expression in this environment,
+    /// which the evaluator does in a loop rather than by recursing
+    Eval(Rc<Value>, Rc<Environment>),
+}
+
+/// A frame of variable bindings, linked to the frame it was created in
+#[derive(Default)]
+pub struct Environment {
+    vars: RefCell<HashMap<String, Rc<Value>>>,
+    /// Docstrings attached to bindings in this frame
+    docs: RefCell<HashMap<String, String>>,
+    /// Methods that `send` can call on foreign values, keyed by Rust type and method name
+    methods: RefCell<HashMap<(TypeId, String), NativeFn>>,
+    parent: Option<Rc<Environment>>,
+}
+
+impl Environment {
+    /// Create a new top-level environment with no bindings
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    /// Create a new empty frame whose lookups fall back to `parent`
+    pub fn with_parent(parent: Rc<Environment>) -> Self {
+        Environment {
+            vars: RefCell::new(HashMap::new()),
+            docs: RefCell::new(HashMap::new()),
+            methods: RefCell::new(HashMap::new()),
+            parent: Some(parent),
+        }
+    }
+
+    /// The enclosing frame, if any
+    pub fn parent(&self) -> Option<&Rc<Environment>> {
+        self.parent.as_ref()
+    }
+
+    /// Look up a name in this frame and then in each enclosing frame
+    pub fn get(&self, name: &str) -> Option<Rc<Value>> {
+        let mut frame = self;
+        loop {
+            if let Some(value) = frame.vars.borrow().get(name) {
+                return Some(value.clone());
+            }
+            frame = frame.parent.as_deref()?;
+        }
+    }
+
+    /// Every name visible from this frame, sorted and without duplicates
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut frame = Some(self);
+        while let Some(current) = frame {
+            names.extend(current.vars.borrow().keys().cloned());
+            frame = current.parent.as_deref();
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Calls `f` on every value bound in this frame. Returns `None` without
+    /// visiting anything if the frame is currently being modified.
+    pub(crate) fn try_for_each_value(&self, mut f: impl FnMut(&Rc<Value>)) -> Option<()> {
+        self.vars.try_borrow().ok()?.values().for_each(&mut f);
+        Some(())
+    }
+
+    /// Removes every binding in this frame, breaking any cycles through it
+    pub(crate) fn clear(&self) {
+        let vars = std::mem::take(&mut *self.vars.borrow_mut());
+        drop(vars);
+    }
+
+    /// Bind a name in this frame only, shadowing any enclosing binding.
+    /// Any docstring attached to an earlier binding of the name is dropped.
+    pub fn define(&self, name: &str, value: Rc<Value>) {
+        self.vars.borrow_mut().insert(name.to_string(), value);
+        self.docs.borrow_mut().remove(name);
+    }
+
+    /// Attach a docstring to a name bound in this frame
+    pub fn set_doc(&self, name: &str, doc: &str) {
+        self.docs.borrow_mut().insert(name.to_string(), doc.to_string());
+    }
+
+    /// The docstring attached to the nearest binding of a name, if any
+    pub fn doc(&self, name: &str) -> Option<String> {
+        let mut frame = self;
+        loop {
+            if frame.vars.borrow().contains_key(name) {
+                return frame.docs.borrow().get(name).cloned();
+            }
+            frame = frame.parent.as_deref()?;
+        }
+    }
+
+    /// Define a method for foreign values of the Rust type `type_id` in this frame
+    pub(crate) fn define_method(&self, type_id: TypeId, name: &str, method: NativeFn) {
+        self.methods.borrow_mut().insert((type_id, name.to_string()), method);
+    }
+
+    /// The nearest method `name` for foreign values of the Rust type `type_id`
+    pub(crate) fn method(&self, type_id: TypeId, name: &str) -> Option<NativeFn> {
+        let key = (type_id, name.to_string());
+        let mut frame = self;
+        loop {
+            if let Some(method) = frame.methods.borrow().get(&key) {
+                return Some(method.clone());
+            }
+            frame = frame.parent.as_deref()?;
+        }
+    }
+
+    /// Update the nearest existing binding of a name, searching outward from this frame
+    pub fn set(&self, name: &str, value: Rc<Value>) -> Result<(), LispError> {
+        let mut frame = self;
+        loop {
+            if let Some(slot) = frame.vars.borrow_mut().get_mut(name) {
+                *slot = value;
+                return Ok(());
+            }
+            frame = frame
+                .parent
+                .as_deref()
+                .ok_or_else(|| LispError::UnboundSymbol(name.to_string()))?;
+        }
+    }
+}
+
+impl std::fmt::Debug for Environment {
+    // Closures stored in a frame refer back to it, so only the names are printed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let vars = self.vars.borrow();
+        let mut names: Vec<&String> = vars.keys().collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("parent", &self.parent)
+            .finish()
+    }
+}
 
 pub fn cons(car: Rc<Value>, cdr: Rc<Value>) -> Rc<Value> {
-    Rc::new(Value::Cons(car, cdr))
+    Rc::new(Value::Cons(RefCell::new(car), RefCell::new(cdr)))
 }
 
 pub fn car(pair: &Rc<Value>) -> Rc<Value> {
     match &**pair {
-        Value::Cons(car, _) => car.clone(),
+        Value::Cons(car, _) => car.borrow().clone(),
         _ => Rc::new(Value::Nil),
     }
 }
 
 pub fn cdr(pair: &Rc<Value>) -> Rc<Value> {
     match &**pair {
-        Value::Cons(_, cdr) => cdr.clone(),
+        Value::Cons(_, cdr) => cdr.borrow().clone(),
         _ => Rc::new(Value::Nil),
     }
 }
+
+/// Replaces the car of a pair in place, returning false if `pair` is not a pair
+pub fn set_car(pair: &Rc<Value>, value: Rc<Value>) -> bool {
+    match &**pair {
+        Value::Cons(car, _) => {
+            *car.borrow_mut() = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Replaces the cdr of a pair in place, returning false if `pair` is not a pair
+pub fn set_cdr(pair: &Rc<Value>, value: Rc<Value>) -> bool {
+    match &**pair {
+        Value::Cons(_, cdr) => {
+            *cdr.borrow_mut() = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Returns false for `#f` and `()`, and true for every other value
+pub fn is_truthy(value: &Rc<Value>) -> bool {
+    !matches!(&**value, Value::Bool(false) | Value::Nil)
+}
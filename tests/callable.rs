@@ -0,0 +1,100 @@
+use rustlisp2::{
+    apply, print_value, read, rust_to_lisp_integer, rust_to_lisp_symbol, Interpreter, LispCallable, LispError, Value,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn arguments_are_not_evaluated_again() {
+    let interp = Interpreter::new();
+    interp.eval_str("(define first (lambda (x) (car x)))").unwrap();
+    interp.eval_str("(define identity (lambda (x) x))").unwrap();
+
+    let list = read("(a b c)").unwrap();
+    assert_eq!(print_value(&interp.call("first", vec![list.clone()]).unwrap()), "a");
+    let symbol = rust_to_lisp_symbol("undefined-symbol");
+    assert_eq!(print_value(&interp.call("identity", vec![symbol]).unwrap()), "undefined-symbol");
+
+    let first = interp.get_global("first").unwrap();
+    assert_eq!(print_value(&apply(&first, &[list]).unwrap()), "a");
+    let plus = interp.get_global("+").unwrap();
+    let sum = apply(&plus, &[rust_to_lisp_integer(2), rust_to_lisp_integer(3)]).unwrap();
+    assert_eq!(print_value(&sum), "5");
+
+    let not_callable = apply(&rust_to_lisp_integer(1), &[]).unwrap_err();
+    assert!(matches!(not_callable, LispError::NotCallable(_)));
+    let arity = interp.apply(&interp.get_global("first").unwrap(), &[]).unwrap_err();
+    assert!(matches!(arity, LispError::ArityMismatch { .. }));
+}
+
+#[test]
+fn typed_handles_can_be_kept_and_called_repeatedly() {
+    let interp = Interpreter::new();
+    interp
+        .eval_str(
+            "(define count-down
+               (lambda (n acc) (if (= n 0) acc (count-down (- n 1) (+ acc 1)))))
+             (define greet (lambda (name) (string-append \"hello \" name)))",
+        )
+        .unwrap();
+
+    let count_down: LispCallable<(i64, i64), i64> = interp.callable("count-down").unwrap();
+    assert_eq!(count_down.call((3, 0)).unwrap(), 3);
+    // The body runs in the evaluator loop, so deep tail recursion is fine
+    assert_eq!(count_down.call((100_000, 0)).unwrap(), 100_000);
+
+    let greet: LispCallable<(&str,), String> = interp.callable("greet").unwrap();
+    assert_eq!(greet.call(("world",)).unwrap(), "hello world");
+
+    let wrong_result: LispCallable<(&str,), i64> = interp.callable("greet").unwrap();
+    assert!(matches!(wrong_result.call(("x",)).unwrap_err(), LispError::Conversion { .. }));
+    assert!(matches!(
+        interp.callable::<(), i64>("missing").unwrap_err(),
+        LispError::UnboundSymbol(_)
+    ));
+}
+
+type Handler = LispCallable<(i64,), i64>;
+
+#[test]
+fn rust_functions_accept_lisp_callbacks() {
+    let interp = Interpreter::new();
+    let handlers: Rc<RefCell<Vec<Handler>>> = Rc::default();
+    let registered = handlers.clone();
+    interp.register_typed_fn("on-tick", move |handler: Handler| {
+        registered.borrow_mut().push(handler);
+    });
+    interp
+        .eval_str("(on-tick (lambda (n) (* n n))) (on-tick (lambda (n) (+ n 1)))")
+        .unwrap();
+
+    let results: Vec<i64> = handlers.borrow().iter().map(|handler| handler.call((7,)).unwrap()).collect();
+    assert_eq!(results, vec![49, 8]);
+
+    let error = interp.eval_str("(on-tick 5)").unwrap_err().kind().to_string();
+    assert_eq!(
+        error,
+        "on-tick: argument 1: Cannot convert 5 to LispCallable<(i64,), i64>: expected a procedure"
+    );
+}
+
+#[test]
+fn handles_give_rust_procedures_the_interpreters_environment() {
+    let interp = Interpreter::new();
+    interp.eval_str("(defmacro twice (x) (list 'begin x x))").unwrap();
+    let form = read("(twice 1)").unwrap();
+
+    let expand: LispCallable<(Rc<Value>,), Rc<Value>> = interp.callable("macroexpand").unwrap();
+    assert_eq!(print_value(&expand.call((form.clone(),)).unwrap()), "(begin 1 1)");
+    assert_eq!(
+        print_value(&interp.apply(&interp.get_global("macroexpand").unwrap(), std::slice::from_ref(&form)).unwrap()),
+        "(begin 1 1)"
+    );
+
+    // A handle made from a bare value sees an empty environment until given one
+    let bare: LispCallable<(Rc<Value>,), Rc<Value>> =
+        LispCallable::new(interp.get_global("macroexpand").unwrap()).unwrap();
+    assert_eq!(print_value(&bare.call((form.clone(),)).unwrap()), "(twice 1)");
+    let bound = bare.with_env(interp.env().clone());
+    assert_eq!(print_value(&bound.call((form,)).unwrap()), "(begin 1 1)");
+}
@@ -0,0 +1,50 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rustlisp2::{eval_str, print_value, setup_environment, Interpreter, LispError};
+
+/// Evaluates a program in a fresh environment and prints its value
+pub fn eval(src: &str) -> Result<String, LispError> {
+    eval_str(src, setup_environment()).map(|value| print_value(&value))
+}
+
+/// Evaluates a program in `interp` and prints its value
+pub fn eval_in(interp: &Interpreter, src: &str) -> Result<String, LispError> {
+    interp.eval_str(src).map(|value| print_value(&value))
+}
+
+/// The error a program fails with, without its location
+pub fn error(src: &str) -> LispError {
+    eval_str(src, setup_environment()).unwrap_err().kind().clone()
+}
+
+/// A directory for the files a test writes, removed with everything in it when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("rustlisp2-test-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    /// Writes `source` to the file `name` in this directory
+    pub fn write(&self, name: &str, source: &str) -> PathBuf {
+        let path = self.path.join(name);
+        fs::write(&path, source).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::eval;
+use rustlisp2::LispError;
+
+#[test]
+fn if_evaluates_only_the_chosen_branch() {
+    assert_eq!(eval("(if #t 1 (car '()))").unwrap(), "1");
+    assert_eq!(eval("(if #f (car '()) 2)").unwrap(), "2");
+    assert_eq!(eval("(if #f 1)").unwrap(), "()");
+    // #f and the empty list are false
+    assert_eq!(eval("(if '() 'yes 'no)").unwrap(), "no");
+    assert_eq!(eval("(if 0 'yes 'no)").unwrap(), "yes");
+    assert!(matches!(eval("(if 1)").unwrap_err().kind(), LispError::ArityMismatch { .. }));
+}
+
+#[test]
+fn cond_picks_the_first_true_clause() {
+    assert_eq!(eval("(cond (#f (car '())) ((= 1 1) 'one 'two) (else 'other))").unwrap(), "two");
+    assert_eq!(eval("(cond (#f 1) (else 2 3))").unwrap(), "3");
+    assert_eq!(eval("(cond (else 1) (#t 2))").unwrap(), "1");
+    // A clause without a body gives the value of its test
+    assert_eq!(eval("(cond (#f) (5))").unwrap(), "5");
+}
+
+#[test]
+fn cond_with_no_matching_clause_is_empty() {
+    assert_eq!(eval("(cond (#f 1) ((= 1 2) 2))").unwrap(), "()");
+    assert_eq!(eval("(cond)").unwrap(), "()");
+}
+
+#[test]
+fn cond_arrow_passes_the_test_value_to_a_procedure() {
+    assert_eq!(eval("(cond ((+ 1 2) => (lambda (x) (* x 10))) (else 0))").unwrap(), "30");
+    assert_eq!(eval("(cond (#f => car) ((cdr '(1 2)) => car))").unwrap(), "2");
+    assert!(matches!(eval("(cond (#t => 1))").unwrap_err().kind(), LispError::NotCallable(_)));
+}
+
+#[test]
+fn when_and_unless_run_their_body_on_a_condition() {
+    assert_eq!(eval("(when (< 1 2) 'a 'b)").unwrap(), "b");
+    assert_eq!(eval("(when #f (car '()))").unwrap(), "()");
+    assert_eq!(eval("(unless #f 'a 'b)").unwrap(), "b");
+    assert_eq!(eval("(unless #t (car '()))").unwrap(), "()");
+    assert_eq!(eval("(unless '() 1)").unwrap(), "1");
+}
+
+#[test]
+fn begin_and_lambda_bodies_run_in_sequence() {
+    assert_eq!(eval("(define n 0) (begin (set! n (+ n 1)) (set! n (* n 10)) n)").unwrap(), "10");
+    assert_eq!(eval("(begin)").unwrap(), "()");
+    assert_eq!(eval("((lambda (x) (define y (* x 2)) (+ x y)) 3)").unwrap(), "9");
+}
+
+#[test]
+fn recursion_terminates_at_its_base_case() {
+    assert_eq!(
+        eval("(define fact (lambda (n) (if (= n 0) 1 (* n (fact (- n 1))))))
+              (fact 20)").unwrap(),
+        "2432902008176640000"
+    );
+}
@@ -0,0 +1,52 @@
+use rustlisp2::{FromLisp, Interpreter, IntoLisp, LispError, print_value};
+use std::collections::HashMap;
+
+#[test]
+fn results_convert_to_rust_types() -> Result<(), LispError> {
+    let interp = Interpreter::new();
+    let n: i64 = interp.eval_str("(+ 40 2)")?.convert()?;
+    let x: f64 = interp.eval_str("(/ 1 4)")?.convert()?;
+    let s: String = interp.eval_str("(string-append \"ab\" \"cd\")")?.convert()?;
+    let flags: Vec<bool> = interp.eval_str("(list #t #f)")?.convert()?;
+    let pair: (String, u8) = interp.eval_str("(list \"x\" 7)")?.convert()?;
+    let missing: Option<i32> = interp.eval_str("'()")?.convert()?;
+    let map: HashMap<String, i64> = interp.eval_str("(list (cons \"a\" 1) (cons \"b\" 2))")?.convert()?;
+
+    assert_eq!(n, 42);
+    assert_eq!(x, 0.25);
+    assert_eq!(s, "abcd");
+    assert_eq!(flags, vec![true, false]);
+    assert_eq!(pair, ("x".to_string(), 7));
+    assert_eq!(missing, None);
+    assert_eq!(map["b"], 2);
+    Ok(())
+}
+
+#[test]
+fn rust_values_convert_to_lisp() {
+    assert_eq!(print_value(&vec![1, 2, 3].into_lisp()), "(1 2 3)");
+    assert_eq!(print_value(&("a", 1.5, true).into_lisp()), "(\"a\" 1.5 #t)");
+    assert_eq!(print_value(&Some(u64::MAX).into_lisp()), "18446744073709551615");
+    assert_eq!(print_value(&None::<i64>.into_lisp()), "()");
+    let map: HashMap<String, i64> = HashMap::from([("k".to_string(), 1)]);
+    assert_eq!(print_value(&map.into_lisp()), "((\"k\" . 1))");
+}
+
+#[test]
+fn conversion_errors_describe_the_problem() {
+    let interp = Interpreter::new();
+    let err = i64::from_lisp(&interp.eval_str("1.5").unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot convert 1.5 to i64: expected an exact integer");
+
+    let err = u8::from_lisp(&interp.eval_str("300").unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot convert 300 to u8: out of range 0..=255");
+
+    let err = Vec::<String>::from_lisp(&interp.eval_str("(list \"a\" 2)").unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cannot convert (\"a\" 2) to Vec<String>: element 1: Cannot convert 2 to String: expected a string"
+    );
+
+    let err = <(i64, i64)>::from_lisp(&interp.eval_str("(list 1)").unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot convert (1) to (i64, i64): expected a list of 2 elements, got 1");
+}
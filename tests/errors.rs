@@ -0,0 +1,63 @@
+mod common;
+
+use common::error;
+use rustlisp2::{eval_str, setup_environment, setup_rust_functions, LispError};
+
+#[test]
+fn unbound_symbols_are_errors_not_nil() {
+    assert!(matches!(error("undefined"), LispError::UnboundSymbol(name) if name == "undefined"));
+    assert!(matches!(error("(+ 1 undefined)"), LispError::UnboundSymbol(_)));
+    assert!(matches!(error("(set! undefined 1)"), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn only_procedures_can_be_called() {
+    assert!(matches!(error("(1 2)"), LispError::NotCallable(value) if value == "1"));
+    assert!(matches!(error("(\"f\")"), LispError::NotCallable(_)));
+    assert!(matches!(error("('(1) 2)"), LispError::NotCallable(_)));
+}
+
+#[test]
+fn wrong_argument_counts_name_the_procedure() {
+    assert!(matches!(
+        error("(car)"),
+        LispError::ArityMismatch { name, expected, got: 0 } if name == "car" && expected == "1"
+    ));
+    assert!(matches!(error("((lambda (x y) x) 1)"), LispError::ArityMismatch { got: 1, .. }));
+    assert!(matches!(error("((lambda (x) x) 1 2)"), LispError::ArityMismatch { got: 2, .. }));
+}
+
+#[test]
+fn wrong_argument_types_say_what_was_expected() {
+    assert!(matches!(
+        error("(+ 1 'a)"),
+        LispError::TypeMismatch { expected, got } if expected == "number" && got == "a"
+    ));
+    assert!(matches!(error("(car 5)"), LispError::TypeMismatch { expected, .. } if expected == "pair"));
+    assert!(matches!(error("(< 1 \"2\")"), LispError::TypeMismatch { .. }));
+}
+
+#[test]
+fn exact_division_by_zero_is_an_error() {
+    assert!(matches!(error("(/ 5 0)"), LispError::DivisionByZero));
+    assert!(matches!(error("(modulo 5 0)"), LispError::DivisionByZero));
+}
+
+#[test]
+fn errors_stop_evaluation_of_the_enclosing_form() {
+    let env = setup_environment();
+    assert!(eval_str("(define x (car 5))", env.clone()).is_err());
+    assert!(matches!(eval_str("x", env).unwrap_err().kind(), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn rust_call_reports_unknown_functions() {
+    let env = setup_environment();
+    setup_rust_functions(env.clone());
+    let err = eval_str("(rust-call no-such-function 1)", env.clone()).unwrap_err();
+    assert_eq!(err.kind().to_string(), "Rust function 'no-such-function' not found");
+    assert!(matches!(
+        eval_str("(rust-call 5)", env).unwrap_err().kind(),
+        LispError::TypeMismatch { .. }
+    ));
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::eval_in;
+use rustlisp2::{
+    lisp_to_rust_foreign, print_value, rust_to_lisp_foreign, Foreign, ForeignRef, ForeignType, Interpreter,
+};
+use std::collections::{HashMap, HashSet};
+
+#[derive(PartialEq)]
+struct Config {
+    path: String,
+    values: HashMap<String, String>,
+}
+
+struct Connection;
+
+/// Describes `Config` for this test's thread, once, before any interpreter is made
+fn register_config() {
+    ForeignType::<Config>::new("config")
+        .display(|config| format!("#<config {}>", config.path))
+        .equality(PartialEq::eq)
+        .hash(|config| config.path.len() as u64)
+        .register();
+}
+
+fn interpreter() -> Interpreter {
+    let interp = Interpreter::new();
+    interp.register_method::<Config, _>("get", |config: ForeignRef<Config>, key: String| {
+        config.values.get(&key).cloned()
+    });
+    interp.register_method::<Config, _>("path", |config: ForeignRef<Config>| config.path.clone());
+    interp.register_typed_fn("load-config", |path: String| {
+        let values = HashMap::from([("name".to_string(), "demo".to_string())]);
+        ForeignRef::new(Config { path, values })
+    });
+    interp.register_typed_fn("config-size", |config: ForeignRef<Config>| config.values.len());
+    interp.register_typed_fn("connect", || ForeignRef::new(Connection));
+    interp
+}
+
+#[test]
+fn scripts_hold_rust_values_and_call_their_methods() {
+    register_config();
+    let interp = interpreter();
+    eval_in(&interp, "(define config (load-config \"app.toml\"))").unwrap();
+    assert_eq!(eval_in(&interp, "config").unwrap(), "#<config app.toml>");
+    assert_eq!(eval_in(&interp, "(foreign? config)").unwrap(), "#t");
+    assert_eq!(eval_in(&interp, "(config-size config)").unwrap(), "1");
+    assert_eq!(eval_in(&interp, "(send config 'get \"name\")").unwrap(), "\"demo\"");
+    assert_eq!(eval_in(&interp, "(send config 'get \"missing\")").unwrap(), "()");
+    assert_eq!(eval_in(&interp, "(send config 'path)").unwrap(), "\"app.toml\"");
+
+    // The host gets the same value back
+    let config = interp.get_global("config").unwrap();
+    let config = lisp_to_rust_foreign::<Config>(&config).unwrap();
+    assert_eq!(config.path, "app.toml");
+    assert!(lisp_to_rust_foreign::<Connection>(&interp.get_global("config").unwrap()).is_none());
+}
+
+#[test]
+fn hooks_decide_printing_and_equality() {
+    register_config();
+    let interp = interpreter();
+    assert_eq!(eval_in(&interp, "(equal? (load-config \"a\") (load-config \"a\"))").unwrap(), "#t");
+    assert_eq!(eval_in(&interp, "(equal? (load-config \"a\") (load-config \"b\"))").unwrap(), "#f");
+    assert_eq!(eval_in(&interp, "(equal? (list 1 \"x\" 'y) (list 1 \"x\" 'y))").unwrap(), "#t");
+    assert_eq!(eval_in(&interp, "(equal? 1 1.0)").unwrap(), "#f");
+
+    // Types without hooks print their Rust name and are only equal to themselves
+    assert_eq!(eval_in(&interp, "(connect)").unwrap(), "#<Connection>");
+    assert_eq!(eval_in(&interp, "(equal? (connect) (connect))").unwrap(), "#f");
+    assert_eq!(eval_in(&interp, "(let ((c (connect))) (equal? c c))").unwrap(), "#t");
+
+    let mut seen = HashSet::new();
+    seen.insert(Foreign::new(Config { path: "a".to_string(), values: HashMap::new() }));
+    assert!(seen.contains(&Foreign::new(Config { path: "a".to_string(), values: HashMap::new() })));
+    assert_eq!(print_value(&rust_to_lisp_foreign(Connection)), "#<Connection>");
+}
+
+#[test]
+fn each_interpreter_keeps_its_own_methods() {
+    register_config();
+    let first = interpreter();
+    let second = interpreter();
+    second.register_method::<Config, _>("path", |config: ForeignRef<Config>| config.path.to_uppercase());
+    second.register_method::<Config, _>("size", |config: ForeignRef<Config>| config.values.len());
+
+    assert_eq!(eval_in(&first, "(send (load-config \"a.toml\") 'path)").unwrap(), "\"a.toml\"");
+    assert_eq!(eval_in(&second, "(send (load-config \"a.toml\") 'path)").unwrap(), "\"A.TOML\"");
+    assert_eq!(eval_in(&second, "(send (load-config \"a.toml\") 'size)").unwrap(), "1");
+    assert!(eval_in(&first, "(send (load-config \"a.toml\") 'size)").is_err());
+
+    // A value handed between interpreters answers to the methods of whichever one calls it
+    let config = first.eval_str("(load-config \"b.toml\")").unwrap();
+    second.set_global("borrowed", config);
+    assert_eq!(eval_in(&second, "(send borrowed 'path)").unwrap(), "\"B.TOML\"");
+
+    // Methods are found from inside procedures too
+    assert_eq!(eval_in(&first, "((lambda (c) (send c 'path)) (load-config \"c\"))").unwrap(), "\"c\"");
+}
+
+#[derive(PartialEq)]
+struct Point(i64, i64);
+
+#[test]
+fn values_equal_by_hook_hash_alike_without_a_hash_hook() {
+    ForeignType::<Point>::new("point").equality(PartialEq::eq).register();
+
+    let mut points = HashSet::new();
+    points.insert(Foreign::new(Point(1, 2)));
+    assert!(points.contains(&Foreign::new(Point(1, 2))));
+    assert!(!points.contains(&Foreign::new(Point(2, 1))));
+    points.insert(Foreign::new(Point(1, 2)));
+    assert_eq!(points.len(), 1);
+}
+
+#[test]
+fn misuse_becomes_lisp_errors() {
+    register_config();
+    let interp = interpreter();
+    let error = |src| eval_in(&interp, src).unwrap_err().kind().to_string();
+    assert_eq!(
+        error("(config-size (connect))"),
+        "config-size: argument 1: Cannot convert #<Connection> to Config: expected a config, got a Connection"
+    );
+    assert_eq!(error("(config-size 5)"), "config-size: argument 1: Cannot convert 5 to Config: expected a foreign value");
+    assert_eq!(error("(send (connect) 'close)"), "Connection has no method 'close'");
+    assert_eq!(error("(send (load-config \"a\") 'get)"), "config.get: expected 2 argument(s), got 1");
+}
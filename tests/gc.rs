@@ -0,0 +1,61 @@
+use rustlisp2::gc::tracked_environments;
+use rustlisp2::{collect_garbage, cons, eval, eval_str, print_value, setup_environment, Value};
+use std::rc::Rc;
+
+const FACT: &str = "
+    (define fact
+      (lambda (n) (if (= n 0) 1 (* n (fact (- n 1))))))
+    (fact 10)";
+
+#[test]
+fn dropped_environments_with_recursive_closures_are_reclaimed() {
+    for _ in 0..1000 {
+        let env = setup_environment();
+        assert_eq!(print_value(&eval_str(FACT, env).unwrap()), "3628800");
+    }
+    collect_garbage();
+    assert_eq!(tracked_environments(), 0);
+}
+
+#[test]
+fn closures_created_inside_calls_stay_bounded() {
+    let env = setup_environment();
+    eval_str(
+        "(define make-countdown
+           (lambda (start)
+             (define loop (lambda (n) (if (= n 0) start (loop (- n 1)))))
+             loop))",
+        env.clone(),
+    )
+    .unwrap();
+
+    // Each call leaves behind a frame that refers to itself through `loop`
+    let result = eval_str(
+        "(let repeat ((i 0))
+           (if (< i 100000)
+               (begin ((make-countdown i) 3) (repeat (+ i 1)))
+               'done))",
+        env.clone(),
+    )
+    .unwrap();
+    assert_eq!(print_value(&result), "done");
+    assert!(tracked_environments() < 25_000, "{} environments alive", tracked_environments());
+
+    collect_garbage();
+    assert!(tracked_environments() <= 2, "{} environments alive", tracked_environments());
+}
+
+#[test]
+fn closures_held_by_the_host_survive_collection() {
+    let env = setup_environment();
+    let fact = eval_str(FACT, env.clone())
+        .and_then(|_| eval_str("fact", env.clone()))
+        .unwrap();
+    drop(env);
+    collect_garbage();
+
+    // The closure still reaches its own binding through the environment it captured
+    let call = cons(fact, cons(Rc::new(Value::Number(5.into())), Rc::new(Value::Nil)));
+    let result = eval(call, setup_environment()).unwrap();
+    assert_eq!(print_value(&result), "120");
+}
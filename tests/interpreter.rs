@@ -0,0 +1,74 @@
+use rustlisp2::{
+    default_registry, eval_str, print_value, register_rust_function, rust_function_names, set_command_line,
+    setup_environment, setup_rust_functions, Interpreter, InterpreterConfig, Value,
+};
+use std::rc::Rc;
+
+fn show(result: Rc<Value>) -> String {
+    print_value(&result)
+}
+
+#[test]
+fn interpreters_on_one_thread_keep_their_own_functions() {
+    let first = Interpreter::new();
+    let second = Interpreter::new();
+    first.register_fn("answer", |_| Ok(Rc::new(Value::Number(1.into()))));
+    second.register_fn("answer", |_| Ok(Rc::new(Value::Number(2.into()))));
+
+    assert_eq!(show(first.eval_str("(answer)").unwrap()), "1");
+    assert_eq!(show(second.eval_str("(rust-call answer)").unwrap()), "2");
+    assert!(Interpreter::new().eval_str("(rust-call answer)").is_err());
+}
+
+#[test]
+fn globals_are_visible_through_the_interpreter() {
+    let interp = Interpreter::new();
+    interp.eval_str("(define double (lambda (x) (* 2 x)))").unwrap();
+    interp.set_global("ten", Rc::new(Value::Number(10.into())));
+
+    assert!(interp.get_global("double").is_some());
+    assert!(interp.get_global("missing").is_none());
+    let result = interp.call("double", vec![interp.get_global("ten").unwrap()]).unwrap();
+    assert_eq!(show(result), "20");
+}
+
+#[test]
+fn config_sets_command_line_and_source_name() {
+    let interp = Interpreter::with_config(InterpreterConfig {
+        source_name: "script.lisp".to_string(),
+        command_line: vec!["script.lisp".to_string(), "--fast".to_string()],
+    });
+    assert_eq!(show(interp.eval_str("(command-line)").unwrap()), "(\"script.lisp\" \"--fast\")");
+
+    let err = interp.eval_str("\n(car 1)").unwrap_err();
+    assert_eq!(err.span().unwrap().to_string(), "script.lisp:2:1");
+}
+
+#[test]
+fn free_functions_share_the_default_registry() {
+    register_rust_function("shared-answer", |_| Ok(Rc::new(Value::Number(42.into()))));
+    assert!(rust_function_names().contains(&"shared-answer".to_string()));
+    assert!(default_registry().get("shared-answer").is_some());
+
+    // Every environment set up with the free functions sees the same registry
+    for _ in 0..2 {
+        let env = setup_environment();
+        setup_rust_functions(env.clone());
+        assert_eq!(print_value(&eval_str("(rust-call shared-answer)", env).unwrap()), "42");
+    }
+
+    // Interpreters keep their own registries apart from it
+    assert!(Interpreter::new().eval_str("(rust-call shared-answer)").is_err());
+    assert!(Interpreter::new().function_names().is_empty());
+}
+
+#[test]
+fn each_environment_has_its_own_command_line() {
+    let first = setup_environment();
+    let second = setup_environment();
+    set_command_line(&first, vec!["first.lisp".to_string(), "-v".to_string()]);
+
+    assert_eq!(print_value(&eval_str("(command-line)", first).unwrap()), "(\"first.lisp\" \"-v\")");
+    assert_eq!(print_value(&eval_str("(command-line)", second).unwrap()), "()");
+    assert_eq!(show(Interpreter::new().eval_str("(command-line)").unwrap()), "()");
+}
@@ -0,0 +1,93 @@
+use rustlisp2::{eval, read, print_value, setup_environment, Environment, LispError, LispResult};
+use std::rc::Rc;
+
+fn run(src: &str, env: &Rc<Environment>) -> LispResult {
+    eval(read(src).expect("parse error"), env.clone())
+}
+
+fn run_to_string(src: &str) -> String {
+    let env = setup_environment();
+    print_value(&run(src, &env).unwrap())
+}
+
+#[test]
+fn let_evaluates_inits_in_the_outer_scope() {
+    assert_eq!(run_to_string("(let ((x 1) (y 2)) (+ x y))"), "3");
+    assert_eq!(run_to_string("(let ((x 1)) (let ((x 2) (y x)) y))"), "1");
+}
+
+#[test]
+fn let_shadows_without_touching_the_outer_binding() {
+    let env = setup_environment();
+    run("(define x 10)", &env).unwrap();
+    assert_eq!(print_value(&run("(let ((x 20)) (define y x) (+ x y))", &env).unwrap()), "40");
+    assert_eq!(print_value(&run("x", &env).unwrap()), "10");
+    assert!(run("y", &env).is_err());
+}
+
+#[test]
+fn let_star_sees_earlier_bindings() {
+    assert_eq!(run_to_string("(let* ((x 1) (y (+ x 1)) (x (* y 10))) x)"), "20");
+    assert_eq!(run_to_string("(let* () 5)"), "5");
+}
+
+#[test]
+fn letrec_supports_mutual_recursion() {
+    assert_eq!(
+        run_to_string(
+            "(letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1)))))
+                      (odd? (lambda (n) (if (= n 0) #f (even? (- n 1))))))
+               (even? 100))"
+        ),
+        "#t"
+    );
+}
+
+#[test]
+fn letrec_variables_shadow_outer_bindings_before_they_are_assigned() {
+    let env = setup_environment();
+    run("(define x 1)", &env).unwrap();
+    let err = run("(letrec ((x 2) (y x)) y)", &env).unwrap_err();
+    assert!(matches!(err.kind(), LispError::Unassigned(name) if name == "x"));
+    let err = run("(letrec* ((y x) (x 2)) y)", &env).unwrap_err();
+    assert!(matches!(err.kind(), LispError::Unassigned(name) if name == "x"));
+
+    // Inits that only capture the variables may refer to them freely
+    assert_eq!(print_value(&run("(letrec ((x 2) (f (lambda () x))) (f))", &env).unwrap()), "2");
+    assert_eq!(print_value(&run("x", &env).unwrap()), "1");
+}
+
+#[test]
+fn letrec_star_binds_sequentially() {
+    assert_eq!(
+        run_to_string("(letrec* ((a 5) (f (lambda () a)) (b (f))) b)"),
+        "5"
+    );
+}
+
+#[test]
+fn named_let_loops_in_constant_stack() {
+    assert_eq!(
+        run_to_string("(let loop ((i 0) (acc 0)) (if (= i 100000) acc (loop (+ i 1) (+ acc 2))))"),
+        "200000"
+    );
+}
+
+#[test]
+fn named_let_name_does_not_leak() {
+    let env = setup_environment();
+    run("(let loop ((i 0)) i)", &env).unwrap();
+    assert!(run("loop", &env).is_err());
+}
+
+#[test]
+fn malformed_bindings_are_errors() {
+    let env = setup_environment();
+    assert!(run("(let (x 1) x)", &env).is_err());
+    assert!(run("(let ((1 2)) 3)", &env).is_err());
+    for src in ["(let ((x 1) (x 2)) x)", "(let loop ((i 0) (i 1)) i)", "(letrec ((f 1) (f 2)) f)", "(let loop)", "(let)"] {
+        assert!(matches!(run(src, &env).unwrap_err().kind(), LispError::Syntax(_)), "{}", src);
+    }
+    // let* may rebind a name, since each binding gets its own frame
+    assert_eq!(run_to_string("(let* ((x 1) (x 2)) x)"), "2");
+}
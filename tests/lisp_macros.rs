@@ -0,0 +1,77 @@
+mod common;
+
+use common::eval_in;
+use rustlisp2::{lisp_fn, lisp_module, Interpreter, LispError};
+
+/// Repeats a string `times` times
+#[lisp_fn(name = "repeat-string")]
+fn repeat(s: String, times: usize) -> String {
+    s.repeat(times)
+}
+
+#[lisp_fn]
+fn safe_div(a: i64, b: i64) -> Result<i64, String> {
+    a.checked_div(b).ok_or_else(|| "division by zero".to_string())
+}
+
+struct Geometry;
+
+#[lisp_module("geo")]
+impl Geometry {
+    /// The area of a circle
+    ///
+    /// Takes the radius.
+    pub fn circle_area(r: f64) -> f64 {
+        std::f64::consts::PI * r * r
+    }
+
+    #[lisp_fn(name = "hyp")]
+    pub fn hypotenuse(a: f64, b: f64) -> f64 {
+        a.hypot(b)
+    }
+
+    /// Not exported, since it is private
+    #[allow(dead_code)]
+    fn helper() -> i64 {
+        0
+    }
+}
+
+fn interpreter() -> Interpreter {
+    let interp = Interpreter::new();
+    register_repeat(interp.env());
+    register_safe_div(interp.env());
+    Geometry::register_lisp_module(interp.env());
+    interp
+}
+
+fn eval(src: &str) -> Result<String, LispError> {
+    eval_in(&interpreter(), src)
+}
+
+#[test]
+fn annotated_functions_are_bound_with_their_lisp_names() {
+    assert_eq!(eval("(repeat-string \"ab\" 3)").unwrap(), "\"ababab\"");
+    assert_eq!(eval("(safe-div 7 2)").unwrap(), "3");
+    assert_eq!(eval("(safe-div 7 0)").unwrap_err().kind().to_string(), "division by zero");
+    assert_eq!(eval("(geo/hyp 3 4)").unwrap(), "5.0");
+    assert_eq!(eval("(< 12.5 (geo/circle-area 2) 12.6)").unwrap(), "#t");
+    assert!(matches!(eval("(geo/helper)").unwrap_err().kind(), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn doc_comments_become_docstrings() {
+    assert_eq!(eval("(documentation 'repeat-string)").unwrap(), "\"Repeats a string `times` times\"");
+    assert_eq!(
+        eval("(documentation 'geo/circle-area)").unwrap(),
+        "\"The area of a circle\\n\\nTakes the radius.\""
+    );
+    assert_eq!(eval("(documentation 'safe-div)").unwrap(), "()");
+
+    // Redefining a name drops the docstring of the old binding
+    assert_eq!(eval("(define repeat-string 1) (documentation 'repeat-string)").unwrap(), "()");
+    assert_eq!(
+        eval("(define f (lambda (x) \"Doubles x\" (* 2 x))) (documentation 'f)").unwrap(),
+        "\"Doubles x\""
+    );
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::eval;
+use rustlisp2::LispError;
+
+#[test]
+fn defmacro_receives_unevaluated_forms() {
+    assert_eq!(
+        eval("(defmacro my-if (c a b) (list 'cond (list c a) (list #t b)))
+              (my-if #t 1 (car '()))").unwrap(),
+        "1"
+    );
+    // The argument is spliced in twice, so its side effects happen twice
+    assert_eq!(
+        eval("(define n 0)
+              (defmacro twice (x) (list 'begin x x))
+              (twice (set! n (+ n 1)))
+              n").unwrap(),
+        "2"
+    );
+    assert_eq!(eval("(defmacro quoted (x) (list 'quote x)) (quoted (a b c))").unwrap(), "(a b c)");
+    assert!(matches!(eval("(defmacro (bad) 1)").unwrap_err().kind(), LispError::TypeMismatch { .. }));
+}
+
+#[test]
+fn macroexpand_expands_until_the_head_is_not_a_macro() {
+    let program = "(defmacro my-unless (c . body) `(if ,c () (begin ,@body)))
+                   (defmacro never (x) `(my-unless #t ,x))";
+    assert_eq!(
+        eval(&format!("{} (macroexpand-1 '(never 1))", program)).unwrap(),
+        "(my-unless #t 1)"
+    );
+    assert_eq!(
+        eval(&format!("{} (macroexpand '(never 1))", program)).unwrap(),
+        "(if #t () (begin 1))"
+    );
+    // Forms that are not macro calls come back unchanged
+    assert_eq!(eval(&format!("{} (macroexpand '(+ 1 2))", program)).unwrap(), "(+ 1 2)");
+    assert_eq!(eval("(macroexpand 'x)").unwrap(), "x");
+}
+
+#[test]
+fn quasiquote_unquotes_and_splices() {
+    assert_eq!(eval("`(1 ,(+ 1 1) ,@(list 3 4) 5)").unwrap(), "(1 2 3 4 5)");
+    assert_eq!(eval("`(1 ,@'() 2)").unwrap(), "(1 2)");
+    assert_eq!(eval("`x").unwrap(), "x");
+}
+
+#[test]
+fn nested_quasiquote_only_unquotes_the_innermost_level() {
+    assert_eq!(
+        eval("`(1 `(2 ,(3 ,(+ 1 3))))").unwrap(),
+        "(1 (quasiquote (2 (unquote (3 4)))))"
+    );
+    assert_eq!(
+        eval("`(a `(b ,(c ,@(list 1 2))))").unwrap(),
+        "(a (quasiquote (b (unquote (c 1 2)))))"
+    );
+    assert_eq!(eval("`(1 `(2 ,(3 4)))").unwrap(), "(1 (quasiquote (2 (unquote (3 4)))))");
+}
+
+#[test]
+fn gensym_makes_fresh_symbols() {
+    assert_eq!(eval("(equal? (gensym) (gensym))").unwrap(), "#f");
+    assert!(eval("(gensym \"tmp\")").unwrap().starts_with("#:tmp"));
+    assert!(eval("(gensym 5)").is_err());
+
+    // A macro that names its temporary with gensym cannot capture the caller's variables
+    assert_eq!(
+        eval("(defmacro swap! (a b)
+                (let ((tmp (gensym)))
+                  `(let ((,tmp ,a)) (set! ,a ,b) (set! ,b ,tmp))))
+              (define tmp 1)
+              (define other 2)
+              (swap! tmp other)
+              (list tmp other)").unwrap(),
+        "(2 1)"
+    );
+}
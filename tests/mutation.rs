@@ -0,0 +1,52 @@
+mod common;
+
+use common::eval;
+use rustlisp2::{Interpreter, LispError};
+
+#[test]
+fn set_updates_the_nearest_binding() {
+    assert_eq!(eval("(define x 1) (set! x 2) x").unwrap(), "2");
+    assert_eq!(eval("(define x 1) (let ((x 10)) (set! x 20)) x").unwrap(), "1");
+    assert_eq!(eval("(define x 1) ((lambda () (set! x 5))) x").unwrap(), "5");
+    assert_eq!(
+        eval("(define make-counter (lambda () (let ((n 0)) (lambda () (set! n (+ n 1)) n))))
+              (define c (make-counter))
+              (c) (c) (c)")
+        .unwrap(),
+        "3"
+    );
+    assert!(matches!(eval("(set! undefined 1)").unwrap_err().kind(), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn pairs_can_be_modified_in_place() {
+    assert_eq!(eval("(define p (cons 1 2)) (set-car! p 'a) (set-cdr! p '(b)) p").unwrap(), "(a b)");
+    assert_eq!(eval("(define p (list 1 2)) (define q p) (set-car! q 9) p").unwrap(), "(9 2)");
+    assert_eq!(eval("(set-car! (list 1) 2)").unwrap(), "()");
+    assert_eq!(eval("(set-cdr! (list 1) 2)").unwrap(), "()");
+    assert!(eval("(set-car! 1 2)").is_err());
+}
+
+#[test]
+fn circular_lists_print_and_compare() {
+    let ring = "(define ring (list 1 2)) (set-cdr! (cdr ring) ring)";
+    assert_eq!(eval(&format!("{} ring", ring)).unwrap(), "(1 2 . ...)");
+    assert_eq!(eval(&format!("{} (car (cdr (cdr ring)))", ring)).unwrap(), "1");
+    assert_eq!(eval("(define p (list 1 2)) (set-car! p p) p").unwrap(), "(... 2)");
+
+    // Shared structure that is not circular prints in full
+    assert_eq!(eval("(define a (list 1)) (list a a)").unwrap(), "((1) (1))");
+
+    let rings = "(define a (list 1 2)) (set-cdr! (cdr a) a)
+                 (define b (list 1 2 1 2)) (set-cdr! (cdr (cdr (cdr b))) b)
+                 (define c (list 1 3)) (set-cdr! (cdr c) c)";
+    assert_eq!(eval(&format!("{} (equal? a b)", rings)).unwrap(), "#t");
+    assert_eq!(eval(&format!("{} (equal? a c)", rings)).unwrap(), "#f");
+}
+
+#[test]
+fn circular_lists_debug_format() {
+    let interp = Interpreter::new();
+    let ring = interp.eval_str("(define ring (list 1 2)) (set-cdr! (cdr ring) ring) ring").unwrap();
+    assert_eq!(format!("{:?}", ring), "Cons(1, (2 1 . ...))");
+}
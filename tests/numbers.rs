@@ -0,0 +1,107 @@
+mod common;
+
+use common::{error, eval};
+use rustlisp2::{LispError, Number};
+
+#[test]
+fn inexact_operands_make_results_inexact() {
+    assert_eq!(eval("(+ 1 2)").unwrap(), "3");
+    assert_eq!(eval("(+ 1 2.0)").unwrap(), "3.0");
+    assert_eq!(eval("(* 1/2 4)").unwrap(), "2");
+    assert_eq!(eval("(+ 1/3 0.5)").unwrap(), "0.8333333333333333");
+    assert_eq!(eval("(- 10000000000000000000000 0.5)").unwrap(), "1e22");
+    assert_eq!(eval("(= 1 1.0)").unwrap(), "#t");
+    assert_eq!(eval("(< 1/3 0.34)").unwrap(), "#t");
+    assert_eq!(eval("(exact? (+ 1/2 1/2))").unwrap(), "#t");
+    assert_eq!(eval("(inexact? (* 0 1.5))").unwrap(), "#t");
+}
+
+#[test]
+fn integers_overflow_into_bignums_and_back() {
+    assert_eq!(eval("(+ 9223372036854775807 1)").unwrap(), "9223372036854775808");
+    assert_eq!(eval("(* 9223372036854775807 2)").unwrap(), "18446744073709551614");
+    assert_eq!(eval("(- -9223372036854775808 1)").unwrap(), "-9223372036854775809");
+    assert_eq!(eval("(- -9223372036854775808)").unwrap(), "9223372036854775808");
+    assert_eq!(eval("(quotient -9223372036854775808 -1)").unwrap(), "9223372036854775808");
+    assert_eq!(eval("(expt 2 100)").unwrap(), "1267650600228229401496703205376");
+
+    // Results that fit again come back as ordinary integers
+    assert_eq!(eval("(- (+ 9223372036854775807 1) 1)").unwrap(), "9223372036854775807");
+    assert_eq!(eval("(integer? (/ (expt 2 100) (expt 2 99)))").unwrap(), "#t");
+    assert_eq!(eval("(/ (expt 2 100) (expt 2 99))").unwrap(), "2");
+}
+
+#[test]
+fn rationals_stay_normalised() {
+    assert_eq!(eval("4/6").unwrap(), "2/3");
+    assert_eq!(eval("-4/6").unwrap(), "-2/3");
+    assert_eq!(eval("(/ 6 4)").unwrap(), "3/2");
+    assert_eq!(eval("(/ 6 -4)").unwrap(), "-3/2");
+    assert_eq!(eval("(/ 6 3)").unwrap(), "2");
+    assert_eq!(eval("(+ 1/2 1/2)").unwrap(), "1");
+    assert_eq!(eval("(* 2/3 3/2)").unwrap(), "1");
+    assert_eq!(eval("(integer? (+ 1/2 1/2))").unwrap(), "#t");
+    assert_eq!(eval("(inexact->exact 0.5)").unwrap(), "1/2");
+    assert_eq!(eval("(exact->inexact 1/4)").unwrap(), "0.25");
+}
+
+#[test]
+fn integer_division_follows_the_sign_rules() {
+    // quotient truncates, remainder takes the dividend's sign, modulo the divisor's
+    assert_eq!(eval("(quotient 7 2)").unwrap(), "3");
+    assert_eq!(eval("(quotient -7 2)").unwrap(), "-3");
+    assert_eq!(eval("(quotient 7 -2)").unwrap(), "-3");
+    assert_eq!(eval("(remainder 7 -2)").unwrap(), "1");
+    assert_eq!(eval("(remainder -7 2)").unwrap(), "-1");
+    assert_eq!(eval("(modulo -7 2)").unwrap(), "1");
+    assert_eq!(eval("(modulo 7 -2)").unwrap(), "-1");
+    assert_eq!(eval("(modulo -7 -2)").unwrap(), "-1");
+    assert_eq!(eval("(quotient 7.0 2)").unwrap(), "3.0");
+    assert_eq!(eval("(modulo -7.0 2)").unwrap(), "1.0");
+    assert_eq!(eval("(modulo (- (expt 2 70)) 3)").unwrap(), "2");
+    assert!(matches!(error("(quotient 7 0)"), LispError::DivisionByZero));
+    assert!(matches!(error("(modulo 1/2 2)"), LispError::TypeMismatch { .. }));
+}
+
+#[test]
+fn expt_handles_negative_and_inexact_exponents() {
+    assert_eq!(eval("(expt 2 10)").unwrap(), "1024");
+    assert_eq!(eval("(expt 2 -2)").unwrap(), "1/4");
+    assert_eq!(eval("(expt 2/3 -2)").unwrap(), "9/4");
+    assert_eq!(eval("(expt -2 3)").unwrap(), "-8");
+    assert_eq!(eval("(expt 2 0)").unwrap(), "1");
+    assert_eq!(eval("(expt 4 0.5)").unwrap(), "2.0");
+    assert_eq!(eval("(expt 2.0 3)").unwrap(), "8.0");
+    assert!(matches!(error("(expt 0 -1)"), LispError::DivisionByZero));
+}
+
+#[test]
+fn only_exact_division_by_zero_is_an_error() {
+    assert!(matches!(error("(/ 1 0)"), LispError::DivisionByZero));
+    assert!(matches!(error("(/ 0)"), LispError::DivisionByZero));
+    assert_eq!(eval("(/ 1 0.0)").unwrap(), "+inf.0");
+    assert_eq!(eval("(/ -1 0.0)").unwrap(), "-inf.0");
+    assert_eq!(eval("(/ 1.5 0)").unwrap(), "+inf.0");
+    assert_eq!(eval("(/ 0.0)").unwrap(), "+inf.0");
+    assert_eq!(eval("(/ 0 0.0)").unwrap(), "+nan.0");
+}
+
+#[test]
+fn numbers_print_in_a_form_that_reads_back() {
+    for literal in [
+        "0", "-7", "9223372036854775807", "-9223372036854775809", "123456789012345678901234567890",
+        "1/3", "-5/7", "2.5", "-0.125", "1.0", "1e22", "1e-7", "+inf.0", "-inf.0", "+nan.0",
+    ] {
+        let number = Number::parse(literal).unwrap();
+        assert_eq!(number.to_string(), literal);
+        let reread = Number::parse(&number.to_string()).unwrap();
+        assert_eq!(reread.to_string(), literal);
+        assert_eq!(reread.is_exact(), number.is_exact(), "{}", literal);
+    }
+
+    assert_eq!(Number::parse(".5").unwrap().to_string(), "0.5");
+    assert_eq!(Number::parse("1e3").unwrap().to_string(), "1000.0");
+    for not_a_number in ["inf", "nan", "-", "+", "1/0", "1/x", "1.2.3", "abc"] {
+        assert!(Number::parse(not_a_number).is_none(), "{}", not_a_number);
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::TempDir;
+use rustlisp2::{eval, eval_file, eval_str, print_value, read, read_all, setup_environment, LispError};
+
+#[test]
+fn read_all_returns_every_form() {
+    let forms = read_all("(define a 1) (define b 2)\n'c 42").unwrap();
+    let printed: Vec<String> = forms.iter().map(print_value).collect();
+    assert_eq!(printed, ["(define a 1)", "(define b 2)", "(quote c)", "42"]);
+    assert!(read_all("").unwrap().is_empty());
+    assert!(read_all("  ; only a comment\n").unwrap().is_empty());
+    assert!(read_all("(define a 1) (car").is_err());
+}
+
+#[test]
+fn read_returns_only_the_first_form() {
+    assert_eq!(print_value(&read("(+ 1 2) (+ 3 4)").unwrap()), "(+ 1 2)");
+}
+
+#[test]
+fn eval_str_runs_forms_in_order_and_returns_the_last_value() {
+    let env = setup_environment();
+    assert_eq!(print_value(&eval_str("(define a 1) (define b (+ a 1)) (* a b 10)", env.clone()).unwrap()), "20");
+    assert_eq!(print_value(&eval(read("b").unwrap(), env).unwrap()), "2");
+    assert_eq!(print_value(&eval_str("", setup_environment()).unwrap()), "()");
+}
+
+#[test]
+fn eval_str_stops_at_the_first_error() {
+    let env = setup_environment();
+    let err = eval_str("(define a 1) (car a) (define b 2) (undefined-thing)", env.clone()).unwrap_err();
+    assert!(matches!(err.kind(), LispError::TypeMismatch { .. }));
+    assert!(eval_str("a", env.clone()).is_ok());
+    assert!(matches!(eval_str("b", env).unwrap_err().kind(), LispError::UnboundSymbol(_)));
+
+    // Nothing runs if the program does not parse
+    let env = setup_environment();
+    assert!(eval_str("(define a 1) (oops", env.clone()).is_err());
+    assert!(eval_str("a", env).is_err());
+}
+
+#[test]
+fn eval_file_runs_a_whole_file() {
+    let dir = TempDir::new();
+    let path = dir.write("whole.lisp", "(define x 20)\n(define y 22)\n(+ x y)\n");
+    let env = setup_environment();
+    assert_eq!(print_value(&eval_file(path.to_str().unwrap(), env.clone()).unwrap()), "42");
+    assert_eq!(print_value(&eval_str("y", env).unwrap()), "22");
+
+    let path = dir.write("broken.lisp", "(define x 1)\n(car x)\n(define y 2)\n");
+    let env = setup_environment();
+    let err = eval_file(path.to_str().unwrap(), env.clone()).unwrap_err();
+    assert!(matches!(err.kind(), LispError::TypeMismatch { .. }));
+    assert_eq!(err.span().unwrap().line, 2);
+    assert!(eval_str("y", env).is_err());
+
+    let err = eval_file("/nonexistent/missing.lisp", setup_environment()).unwrap_err();
+    assert!(err.to_string().starts_with("Cannot read /nonexistent/missing.lisp"));
+}
+
+#[test]
+fn load_defines_globally() {
+    let dir = TempDir::new();
+    let path = dir.write("library.lisp", "(define square (lambda (n) (* n n)))\n(define loaded 'yes)\n");
+    let env = setup_environment();
+    let program = format!("(let ((unused 0)) (load {:?}))\n(square 7)", path.to_str().unwrap());
+    assert_eq!(print_value(&eval_str(&program, env.clone()).unwrap()), "49");
+    assert_eq!(print_value(&eval_str("loaded", env).unwrap()), "yes");
+    assert!(eval_str("(load \"/nonexistent/missing.lisp\")", setup_environment()).is_err());
+}
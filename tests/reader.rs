@@ -0,0 +1,79 @@
+mod common;
+
+use common::eval;
+use rustlisp2::{read, LispError};
+
+fn syntax_error(src: &str) -> String {
+    read(src).unwrap_err().kind().to_string()
+}
+
+#[test]
+fn dotted_lists_read_as_improper_lists() {
+    assert_eq!(eval("(cdr '(1 . 2))").unwrap(), "2");
+    assert_eq!(eval("'(1 2 . 3)").unwrap(), "(1 2 . 3)");
+    assert_eq!(eval("'(1 . (2 3))").unwrap(), "(1 2 3)");
+    assert_eq!(eval("`(a . ,(+ 1 2))").unwrap(), "(a . 3)");
+
+    // A dot that is part of an atom is not the dotted-pair marker
+    assert_eq!(eval("'(a ... .5)").unwrap(), "(a ... 0.5)");
+}
+
+#[test]
+fn misplaced_dots_are_syntax_errors() {
+    assert_eq!(syntax_error("(. 1)"), "Syntax error: Unexpected '.' at the start of a list");
+    assert_eq!(syntax_error("(1 . )"), "Syntax error: Expected a form after '.'");
+    assert_eq!(syntax_error("(1 . 2 3)"), "Syntax error: Expected ')' after the form following '.'");
+    assert_eq!(syntax_error("."), "Syntax error: Unexpected '.' outside a list");
+    assert!(matches!(read("(1 . 2").unwrap_err().kind(), LispError::Incomplete(_)));
+}
+
+#[test]
+fn lambdas_take_rest_parameters() {
+    assert_eq!(eval("((lambda (a . rest) rest) 1 2 3)").unwrap(), "(2 3)");
+    assert_eq!(eval("((lambda (a . rest) rest) 1)").unwrap(), "()");
+    assert_eq!(eval("((lambda args args) 1 2)").unwrap(), "(1 2)");
+    assert_eq!(
+        eval("((lambda (a b . rest) a) 1)").unwrap_err().kind().to_string(),
+        "lambda: expected at least 2 argument(s), got 1"
+    );
+}
+
+#[test]
+fn macros_take_rest_parameters() {
+    let when = "(define-macro (my-when c . body) `(if ,c (begin ,@body) nil))";
+    assert_eq!(eval(&format!("{} (my-when #t 1 2)", when)).unwrap(), "2");
+    assert_eq!(eval(&format!("{} (my-when #f 1 2)", when)).unwrap(), "()");
+    assert_eq!(
+        eval(&format!("{} (macroexpand-1 '(my-when #t 1 2 3))", when)).unwrap(),
+        "(if #t (begin 1 2 3) nil)"
+    );
+}
+
+#[test]
+fn block_comments_nest() {
+    assert_eq!(eval("#| outer #| inner |# still outer |# 42").unwrap(), "42");
+    assert_eq!(eval("(+ 1 #| two #| 2 |# |# 3)").unwrap(), "4");
+    assert_eq!(eval("'(a #|x|# b)").unwrap(), "(a b)");
+}
+
+#[test]
+fn datum_comments_discard_one_form() {
+    assert_eq!(eval("(list 1 #;(car '()) 2)").unwrap(), "(1 2)");
+    assert_eq!(eval("(list 1 2 #;3)").unwrap(), "(1 2)");
+    assert_eq!(eval("(list 1 #; #;2 3 4)").unwrap(), "(1 4)");
+    assert!(matches!(read("(list 1 #;)").unwrap_err().kind(), LispError::Syntax(_)));
+}
+
+#[test]
+fn line_comments_may_end_the_input() {
+    assert_eq!(eval("(+ 1 2) ; the sum").unwrap(), "3");
+    assert_eq!(eval("(+ 1 ; one\n 2)").unwrap(), "3");
+    assert_eq!(rustlisp2::read_all("; nothing but a comment").unwrap().len(), 0);
+}
+
+#[test]
+fn unterminated_block_comments_are_incomplete() {
+    assert!(matches!(read("#| never closed").unwrap_err().kind(), LispError::Incomplete(_)));
+    assert!(matches!(read("(+ 1 #| #| |# 2)").unwrap_err().kind(), LispError::Incomplete(_)));
+    assert_eq!(syntax_error("#| never closed"), "Syntax error: Expected '|#' but got end of input");
+}
@@ -0,0 +1,68 @@
+use rustlisp2::repl::{meta_command, REPL_HELP};
+use rustlisp2::{Interpreter, LispError};
+
+/// Runs a command and returns what it wrote
+fn run(interp: &mut Interpreter, line: &str) -> Result<String, LispError> {
+    let mut out = Vec::new();
+    meta_command(line, interp, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn type_shows_the_kind_of_each_value() {
+    let mut interp = Interpreter::new();
+    assert_eq!(run(&mut interp, ":type 1").unwrap(), "number\n");
+    assert_eq!(run(&mut interp, ":type \"s\" 'a '(1) car (lambda (x) x)").unwrap(), "string\nsymbol\npair\nprocedure\nlambda\n");
+    assert_eq!(run(&mut interp, ":type if").unwrap(), "special form\n");
+    assert!(matches!(run(&mut interp, ":type undefined").unwrap_err().kind(), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn expand_shows_the_full_expansion() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("(defmacro my-unless (c . body) `(if ,c () (begin ,@body)))
+                   (defmacro never (x) `(my-unless #t ,x))")
+        .unwrap();
+    assert_eq!(run(&mut interp, ":expand (never (display 1))").unwrap(), "(if #t () (begin (display 1)))\n");
+    assert_eq!(run(&mut interp, ":expand (+ 1 2)").unwrap(), "(+ 1 2)\n");
+}
+
+#[test]
+fn doc_describes_a_binding() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("(define square (lambda (x) \"Multiplies x by itself\" (* x x)))")
+        .unwrap();
+    assert_eq!(run(&mut interp, ":doc square").unwrap(), "square: lambda (x)\n  Multiplies x by itself\n");
+    assert_eq!(run(&mut interp, ":doc car").unwrap(), "car: procedure\n");
+    assert!(matches!(run(&mut interp, ":doc nothing").unwrap_err().kind(), LispError::UnboundSymbol(_)));
+}
+
+#[test]
+fn reset_discards_definitions() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("(define x 1)").unwrap();
+    assert_eq!(run(&mut interp, ":reset").unwrap(), "Environment reset\n");
+    assert!(interp.get_global("x").is_none());
+    assert!(interp.get_global("car").is_some());
+}
+
+#[test]
+fn env_help_time_and_unknown_commands() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("(define answer 42)").unwrap();
+    let env = run(&mut interp, ":env").unwrap();
+    assert!(env.lines().any(|line| line.split_whitespace().eq(["answer", "number"])));
+    assert!(env.lines().any(|line| line.starts_with("car ") && line.ends_with(" procedure")));
+
+    assert_eq!(run(&mut interp, ":help").unwrap(), format!("{}\n", REPL_HELP));
+
+    let timed = run(&mut interp, ":time (define y 2) (* answer y)").unwrap();
+    assert!(timed.starts_with("84\nElapsed: "));
+
+    assert_eq!(
+        run(&mut interp, ":frobnicate").unwrap_err().to_string(),
+        "Unknown command :frobnicate, type :help for a list"
+    );
+}
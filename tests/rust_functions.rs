@@ -0,0 +1,62 @@
+use rustlisp2::{
+    define_rust_function, eval_str, print_value, register_rust_function, rust_procedure, setup_environment,
+    setup_rust_functions, Interpreter, LispError, Value,
+};
+use std::rc::Rc;
+
+/// Sums its arguments, failing on anything that is not an integer
+fn sum(args: Vec<Rc<Value>>) -> Result<Rc<Value>, LispError> {
+    let mut total = 0;
+    for arg in &args {
+        match &**arg {
+            Value::Number(n) if n.to_i64().is_some() => total += n.to_i64().unwrap(),
+            _ => return Err(LispError::Custom(format!("sum: not an integer: {}", print_value(arg)))),
+        }
+    }
+    Ok(Rc::new(Value::Number(total.into())))
+}
+
+#[test]
+fn errors_from_registered_functions_reach_the_script() {
+    let interp = Interpreter::new();
+    interp.register_fn("sum", sum);
+
+    assert_eq!(print_value(&interp.eval_str("(sum 1 2 3)").unwrap()), "6");
+    assert_eq!(print_value(&interp.eval_str("(rust-call sum 4 5)").unwrap()), "9");
+
+    let err = interp.eval_str("(sum 1 'two)").unwrap_err();
+    assert_eq!(err.kind().to_string(), "sum: not an integer: two");
+    let err = interp.eval_str("(rust-call sum 1.5)").unwrap_err();
+    assert_eq!(err.kind().to_string(), "sum: not an integer: 1.5");
+
+    // The error stops the rest of the program
+    assert!(interp.eval_str("(define before 1) (sum \"x\") (define after 2)").is_err());
+    assert!(interp.get_global("before").is_some());
+    assert!(interp.get_global("after").is_none());
+}
+
+#[test]
+fn free_functions_return_errors_too() {
+    let env = setup_environment();
+    setup_rust_functions(env.clone());
+    register_rust_function("checked-sum", sum);
+    define_rust_function(&env, "direct-sum", sum);
+    env.define("wrapped-sum", rust_procedure("wrapped-sum", sum));
+
+    assert_eq!(print_value(&eval_str("(rust-call checked-sum 1 2)", env.clone()).unwrap()), "3");
+    assert_eq!(print_value(&eval_str("((lambda (f) (f 1 2 3)) direct-sum)", env.clone()).unwrap()), "6");
+    for call in ["(rust-call checked-sum 'x)", "(direct-sum 'x)", "(wrapped-sum 'x)"] {
+        let err = eval_str(call, env.clone()).unwrap_err();
+        assert!(matches!(err.kind(), LispError::Custom(message) if message == "sum: not an integer: x"), "{}", call);
+    }
+}
+
+#[test]
+fn errors_from_rust_functions_carry_the_call_location() {
+    let interp = Interpreter::new();
+    interp.register_fn("fail", |_| Err(LispError::DivisionByZero));
+    let err = interp.eval_str("(+ 1\n   (fail))").unwrap_err();
+    assert!(matches!(err.kind(), LispError::DivisionByZero));
+    assert_eq!(err.span().unwrap().line, 2);
+    assert_eq!(err.span().unwrap().column, 4);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::TempDir;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn rustlisp2() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_rustlisp2"))
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn scripts_see_their_command_line_and_skip_the_shebang() {
+    let dir = TempDir::new();
+    let path = dir.write(
+        "args.lisp",
+        "#!/usr/bin/env rustlisp2\n(display (cdr (command-line)))\n(newline)\n",
+    );
+    let output = rustlisp2().arg(&path).args(["one", "two words"]).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "(\"one\" \"two words\")\n");
+
+    let output = rustlisp2().arg(&path).output().unwrap();
+    assert_eq!(stdout(&output), "()\n");
+
+    let path = dir.write("name.lisp", "(display (car (command-line)))");
+    let output = rustlisp2().arg(&path).output().unwrap();
+    assert_eq!(stdout(&output), path.to_str().unwrap());
+}
+
+#[test]
+fn failing_scripts_exit_with_an_error_status() {
+    let dir = TempDir::new();
+    let path = dir.write("fails.lisp", "(display \"before\")\n(car 5)\n(display \"after\")\n");
+    let output = rustlisp2().arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "before");
+    assert!(stderr(&output).starts_with(&format!("Error: {}:2:1: Type mismatch", path.display())));
+
+    let output = rustlisp2().arg("/nonexistent/missing.lisp").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Cannot read /nonexistent/missing.lisp"));
+}
+
+#[test]
+fn one_liners_print_their_result() {
+    let output = rustlisp2().args(["-e", "(+ 1 2) (* 6 7)"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "42\n");
+
+    let output = rustlisp2().args(["-e", "(command-line)", "a", "b"]).output().unwrap();
+    assert_eq!(stdout(&output), "(\"-e\" \"a\" \"b\")\n");
+
+    let output = rustlisp2().args(["-e", "(undefined-thing)"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Unbound symbol: undefined-thing"));
+
+    let output = rustlisp2().arg("-e").output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("Usage:"));
+}
+
+#[test]
+fn programs_piped_to_stdin_are_run() {
+    for args in [&[][..], &["-"][..]] {
+        let mut child = rustlisp2()
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"(define x 2)\n(display (* x 21))").unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert_eq!(stdout(&output), "42");
+    }
+}
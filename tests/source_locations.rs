@@ -0,0 +1,41 @@
+use rustlisp2::{eval_str_named, read_all_named, setup_environment, LispError};
+
+fn error(src: &str) -> LispError {
+    eval_str_named(src, "test.lisp", setup_environment()).unwrap_err()
+}
+
+#[test]
+fn parse_errors_point_at_the_offending_character() {
+    let err = read_all_named("(+ 1 2)\n  (display \"a\\q\")", "test.lisp").unwrap_err();
+    assert!(matches!(err.kind(), LispError::Syntax(_)));
+    assert_eq!(err.span().unwrap().to_string(), "test.lisp:2:14");
+    assert_eq!(
+        err.to_string(),
+        "test.lisp:2:14: Syntax error: Unknown escape sequence '\\q' in string\n      (display \"a\\q\")\n                 ^"
+    );
+
+    // An unclosed list is reported where it was opened
+    let err = read_all_named("(define x\n  (list 1 2)", "test.lisp").unwrap_err();
+    assert!(matches!(err.kind(), LispError::Incomplete(_)));
+    assert_eq!(err.span().unwrap().to_string(), "test.lisp:1:1");
+}
+
+#[test]
+fn runtime_errors_inside_a_lambda_point_into_its_body() {
+    let err = error("(define f\n  (lambda (x)\n    (+ x (car x))))\n(f 5)");
+    assert!(matches!(err.kind(), LispError::TypeMismatch { .. }));
+    assert_eq!(err.span().unwrap().to_string(), "test.lisp:3:10");
+    assert_eq!(err.span().unwrap().source_line(), "    (+ x (car x))))");
+    assert_eq!(err.span().unwrap().snippet(), "        (+ x (car x))))\n             ^");
+}
+
+#[test]
+fn errors_from_a_macro_expansion_point_at_the_macro_call() {
+    let err = error("(defmacro first-of (x) (list 'car x))\n\n(first-of 5)");
+    assert!(matches!(err.kind(), LispError::TypeMismatch { .. }));
+    assert_eq!(err.span().unwrap().to_string(), "test.lisp:3:1");
+    assert_eq!(
+        err.to_string(),
+        "test.lisp:3:1: Type mismatch: expected pair, got 5\n    (first-of 5)\n    ^"
+    );
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::eval;
+use rustlisp2::{eval_str, lisp_to_rust_string, read, rust_to_lisp_string, setup_environment, LispError, Value};
+
+fn string(src: &str) -> String {
+    match &*eval_str(src, setup_environment()).unwrap() {
+        Value::String(s) => s.clone(),
+        other => panic!("expected a string, got {:?}", other),
+    }
+}
+
+#[test]
+fn escapes_are_decoded_when_read_and_encoded_when_printed() {
+    assert_eq!(string(r#""tab\there""#), "tab\there");
+    assert_eq!(string(r#""a\nb\r\0""#), "a\nb\r\0");
+    assert_eq!(string(r#""say \"hi\" \\ bye""#), "say \"hi\" \\ bye");
+    assert_eq!(eval(r#""line\none""#).unwrap(), r#""line\none""#);
+    assert_eq!(eval(r#""q\"\\""#).unwrap(), r#""q\"\\""#);
+    assert_eq!(string("\"spans\nlines\""), "spans\nlines");
+}
+
+#[test]
+fn bad_strings_are_reader_errors() {
+    assert_eq!(
+        read(r#""bad \q""#).unwrap_err().kind().to_string(),
+        "Syntax error: Unknown escape sequence '\\q' in string"
+    );
+    assert!(matches!(read(r#""unterminated"#).unwrap_err().kind(), LispError::Incomplete(_)));
+    assert!(matches!(read(r#""ends in \"#).unwrap_err().kind(), LispError::Incomplete(_)));
+}
+
+#[test]
+fn strings_are_distinct_from_symbols() {
+    assert_eq!(eval(r#"(string? "abc")"#).unwrap(), "#t");
+    assert_eq!(eval("(string? 'abc)").unwrap(), "#f");
+    assert_eq!(eval(r#"(equal? "abc" 'abc)"#).unwrap(), "#f");
+    assert_eq!(eval(r#"(string->symbol "abc")"#).unwrap(), "abc");
+    assert_eq!(eval("(symbol->string 'abc)").unwrap(), r#""abc""#);
+    assert!(eval("(string-length 'abc)").is_err());
+}
+
+#[test]
+fn string_procedures() {
+    assert_eq!(eval(r#"(string-length "héllo")"#).unwrap(), "5");
+    assert_eq!(eval(r#"(string-append "foo" "" "bar")"#).unwrap(), r#""foobar""#);
+    assert_eq!(eval("(string-append)").unwrap(), r#""""#);
+    assert_eq!(eval(r#"(substring "héllo" 1 3)"#).unwrap(), r#""él""#);
+    assert_eq!(eval(r#"(substring "hello" 2)"#).unwrap(), r#""llo""#);
+    assert!(eval(r#"(substring "hello" 3 9)"#).is_err());
+    assert!(eval(r#"(substring "hello" 3 2)"#).is_err());
+    assert_eq!(eval(r#"(string-split "a,b,,c" ",")"#).unwrap(), r#"("a" "b" "" "c")"#);
+    assert_eq!(eval(r#"(string-split "  a  b ")"#).unwrap(), r#"("a" "b")"#);
+    assert!(eval(r#"(string-split "abc" "")"#).is_err());
+    assert_eq!(eval(r#"(string=? "abc" "abc")"#).unwrap(), "#t");
+    assert_eq!(eval(r#"(string=? "abc" "abd")"#).unwrap(), "#f");
+}
+
+#[test]
+fn strings_convert_to_and_from_numbers() {
+    assert_eq!(eval(r#"(string->number "42")"#).unwrap(), "42");
+    assert_eq!(eval(r#"(string->number " 1/2 ")"#).unwrap(), "1/2");
+    assert_eq!(eval(r#"(string->number "2.5")"#).unwrap(), "2.5");
+    assert_eq!(eval(r#"(string->number "forty")"#).unwrap(), "#f");
+    assert_eq!(eval("(number->string 3/4)").unwrap(), r#""3/4""#);
+    assert_eq!(eval("(number->string (+ 0.5 1))").unwrap(), r#""1.5""#);
+    assert!(eval(r#"(number->string "3")"#).is_err());
+}
+
+#[test]
+fn interop_converts_strings_not_symbols() {
+    assert_eq!(lisp_to_rust_string(&rust_to_lisp_string("hi")).as_deref(), Some("hi"));
+    assert_eq!(lisp_to_rust_string(&read("sym").unwrap()), None);
+}
@@ -0,0 +1,41 @@
+use rustlisp2::{eval, read, print_value, setup_environment, Environment, LispResult};
+use std::rc::Rc;
+
+fn run(src: &str, env: &Rc<Environment>) -> LispResult {
+    eval(read(src).expect("parse error"), env.clone())
+}
+
+#[test]
+fn million_iteration_tail_recursive_loop() {
+    let env = setup_environment();
+    run(
+        "(define count-down (lambda (n acc) (if (= n 0) acc (count-down (- n 1) (+ acc 1)))))",
+        &env,
+    )
+    .unwrap();
+    let result = run("(count-down 1000000 0)", &env).unwrap();
+    assert_eq!(print_value(&result), "1000000");
+}
+
+#[test]
+fn tail_calls_through_cond_when_and_begin() {
+    let env = setup_environment();
+    run(
+        "(define loop (lambda (n)
+           (cond ((= n 0) (quote done))
+                 (else (begin (when #t (loop (- n 1))))))))",
+        &env,
+    )
+    .unwrap();
+    let result = run("(loop 100000)", &env).unwrap();
+    assert_eq!(print_value(&result), "done");
+}
+
+#[test]
+fn mutual_tail_recursion() {
+    let env = setup_environment();
+    run("(define even? (lambda (n) (if (= n 0) #t (odd? (- n 1)))))", &env).unwrap();
+    run("(define odd? (lambda (n) (if (= n 0) #f (even? (- n 1)))))", &env).unwrap();
+    let result = run("(even? 100001)", &env).unwrap();
+    assert_eq!(print_value(&result), "#f");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::eval_in;
+use rustlisp2::{rust_add, Interpreter, LispError, Rest};
+
+fn interpreter() -> Interpreter {
+    let interp = Interpreter::new();
+    interp.register_typed_fn("hypot", |a: f64, b: f64| a.hypot(b));
+    interp.register_typed_fn("shout", |s: String| -> Result<String, String> {
+        if s.is_empty() {
+            Err("nothing to shout".to_string())
+        } else {
+            Ok(s.to_uppercase())
+        }
+    });
+    interp.register_typed_fn("count", |items: Rest<i64>| items.0.len());
+    interp.register_typed_fn("rust-add", rust_add);
+    interp
+}
+
+fn eval(src: &str) -> Result<String, LispError> {
+    eval_in(&interpreter(), src)
+}
+
+fn error(src: &str) -> String {
+    eval(src).unwrap_err().kind().to_string()
+}
+
+#[test]
+fn typed_functions_convert_arguments_and_results() {
+    assert_eq!(eval("(hypot 3 4)").unwrap(), "5.0");
+    assert_eq!(eval("(shout \"hi\")").unwrap(), "\"HI\"");
+    assert_eq!(eval("(count)").unwrap(), "0");
+    assert_eq!(eval("(count 1 2 3)").unwrap(), "3");
+    assert_eq!(eval("(rust-add 1 2 3/2)").unwrap(), "9/2");
+}
+
+#[test]
+fn typed_functions_are_first_class() {
+    assert_eq!(eval("((lambda (f) (f 6 8)) hypot)").unwrap(), "10.0");
+    assert_eq!(eval("(rust-call hypot 6 8)").unwrap(), "10.0");
+}
+
+#[test]
+fn wrong_arguments_become_lisp_errors() {
+    let err = eval("(hypot 3)").unwrap_err();
+    assert!(matches!(err.kind(), LispError::ArityMismatch { name, got: 1, .. } if name == "hypot"));
+    let err = eval("(rust-add 1)").unwrap_err();
+    assert!(matches!(err.kind(), LispError::ArityMismatch { expected, .. } if expected == "at least 2"));
+
+    let err = eval("(hypot 3 \"4\")").unwrap_err();
+    let LispError::Argument { function, index, error: cause } = err.kind() else {
+        panic!("expected an argument error, got {:?}", err);
+    };
+    assert_eq!((function.as_str(), *index), ("hypot", 2));
+    assert!(matches!(&**cause, LispError::Conversion { target, value, .. } if target == "f64" && value == "\"4\""));
+
+    let err = eval("(count 1 2 'x)").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        LispError::Argument { index: 3, error, .. } if matches!(&**error, LispError::Conversion { target, .. } if target == "i64")
+    ));
+
+    // The message still names the function and the argument
+    assert_eq!(
+        error("(count 1 2 'x)"),
+        "count: argument 3: Cannot convert x to i64: expected an exact integer"
+    );
+    assert!(matches!(eval("(shout \"\")").unwrap_err().kind(), LispError::Custom(message) if message == "nothing to shout"));
+}